@@ -0,0 +1,197 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{sync::Notify, time::Instant};
+
+#[derive(Debug)]
+struct State {
+    limit: f64,
+    in_flight: usize,
+    paused_until: Option<Instant>,
+}
+
+/// Paces bulk queries by the API's feedback instead of a fixed concurrency: the number
+/// of queries in flight grows by one for every window of successes and halves on
+/// `429 Too Many Requests`, and new queries wait out `Retry-After` or an exhausted
+/// `RateLimit` header. Clones share the state.
+#[derive(Debug, Clone)]
+pub struct AdaptiveLimiter {
+    min: usize,
+    max: usize,
+    state: Arc<Mutex<State>>,
+    released: Arc<Notify>,
+}
+
+/// Holds a query slot until dropped.
+#[derive(Debug)]
+pub struct AdaptivePermit {
+    limiter: AdaptiveLimiter,
+}
+
+impl Drop for AdaptivePermit {
+    fn drop(&mut self) {
+        self.limiter.state.lock().unwrap().in_flight -= 1;
+        self.limiter.released.notify_waiters();
+    }
+}
+
+impl AdaptiveLimiter {
+    /// Start at `min` queries in flight, growing to at most `max`.
+    pub fn new(min: usize, max: usize) -> Self {
+        let min = min.max(1);
+        Self {
+            min,
+            max: max.max(min),
+            state: Arc::new(Mutex::new(State {
+                limit: min as f64,
+                in_flight: 0,
+                paused_until: None,
+            })),
+            released: Arc::new(Notify::new()),
+        }
+    }
+
+    /// The most queries that will ever be in flight.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// The number of queries currently allowed in flight.
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit as usize
+    }
+
+    /// Wait for a slot under the current limit and outside any pause.
+    pub async fn acquire(&self) -> AdaptivePermit {
+        loop {
+            let released = self.released.notified();
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                match state.paused_until.filter(|until| *until > now) {
+                    Some(until) => Some(until - now),
+                    None if state.in_flight < state.limit as usize => {
+                        state.in_flight += 1;
+                        return AdaptivePermit {
+                            limiter: self.clone(),
+                        };
+                    }
+                    None => None,
+                }
+            };
+            match wait {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => released.await,
+            }
+        }
+    }
+
+    /// Feed back a response: started queries grow the limit, `429`s shrink it, and a
+    /// delay from the response headers pauses new queries.
+    pub(crate) fn observe(&self, throttled: bool, query_started: bool, pause: Option<Duration>) {
+        let mut state = self.state.lock().unwrap();
+        if throttled {
+            state.limit = (state.limit / 2.0).max(self.min as f64);
+        } else if query_started {
+            state.limit = (state.limit + 1.0 / state.limit).min(self.max as f64);
+        }
+        if let Some(pause) = pause {
+            let until = Instant::now() + pause;
+            state.paused_until = Some(state.paused_until.map_or(until, |u| u.max(until)));
+        }
+    }
+}
+
+/// The time until the rate limit window resets when no requests remain in it, from
+/// either the combined `RateLimit: limit=10, remaining=0, reset=30` header or the
+/// separate `RateLimit-Remaining` and `RateLimit-Reset` headers.
+pub(crate) fn rate_limit_reset(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name)?.to_str().ok().map(str::trim);
+    let (remaining, reset) = match header("ratelimit") {
+        Some(combined) => {
+            let field = |name: &str| {
+                combined.split([',', ';']).find_map(|part| {
+                    let (key, value) = part.split_once('=')?;
+                    (key.trim() == name).then(|| value.trim().to_string())
+                })
+            };
+            (field("remaining"), field("reset"))
+        }
+        None => (
+            header("ratelimit-remaining").map(str::to_string),
+            header("ratelimit-reset").map(str::to_string),
+        ),
+    };
+    let remaining = remaining?.parse::<u64>().ok()?;
+    let reset = reset?.parse::<u64>().ok()?;
+    (remaining == 0).then(|| Duration::from_secs(reset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_limit_grows_by_about_one_per_window_and_halves_when_throttled() {
+        let limiter = AdaptiveLimiter::new(2, 4);
+        // Each success adds 1/limit: 2.5, 2.9, then 3.24
+        for _ in 0..2 {
+            limiter.observe(false, true, None);
+            assert_eq!(limiter.limit(), 2);
+        }
+        limiter.observe(false, true, None);
+        assert_eq!(limiter.limit(), 3);
+        for _ in 0..10 {
+            limiter.observe(false, true, None);
+        }
+        assert_eq!(limiter.limit(), 4);
+        limiter.observe(true, false, None);
+        assert_eq!(limiter.limit(), 2);
+        limiter.observe(true, false, None);
+        assert_eq!(limiter.limit(), 2);
+        // Other successful requests don't grow the limit
+        limiter.observe(false, false, None);
+        assert_eq!(limiter.limit(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn acquire_waits_for_a_slot() {
+        let limiter = AdaptiveLimiter::new(1, 1);
+        let permit = limiter.acquire().await;
+        let waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire().await }
+        });
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(!waiting.is_finished());
+        drop(permit);
+        waiting.await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn acquire_waits_out_a_pause() {
+        let limiter = AdaptiveLimiter::new(1, 1);
+        limiter.observe(true, false, Some(Duration::from_secs(30)));
+        limiter.observe(true, false, Some(Duration::from_secs(10)));
+        let started = Instant::now();
+        drop(limiter.acquire().await);
+        assert_eq!(started.elapsed(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn an_exhausted_rate_limit_header_pauses_until_reset() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            "ratelimit",
+            "limit=10, remaining=0, reset=30".parse().unwrap(),
+        );
+        assert_eq!(rate_limit_reset(&headers), Some(Duration::from_secs(30)));
+        headers.insert(
+            "ratelimit",
+            "limit=10, remaining=3, reset=30".parse().unwrap(),
+        );
+        assert_eq!(rate_limit_reset(&headers), None);
+    }
+}
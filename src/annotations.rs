@@ -0,0 +1,86 @@
+use std::{collections::BTreeMap, fs, io::ErrorKind, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::honeycomb::Column;
+
+/// Local tags for a column. The Honeycomb API has no column tagging so these are
+/// kept in a JSON file alongside the tooling.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct ColumnTags {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub pii: bool,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AnnotatedColumn {
+    #[serde(flatten)]
+    pub column: Column,
+    pub tags: ColumnTags,
+}
+
+/// Column tags keyed by dataset slug and then column key name.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Annotations {
+    datasets: BTreeMap<String, BTreeMap<String, ColumnTags>>,
+}
+
+impl Annotations {
+    /// Load annotations from a JSON file. A missing file is treated as empty.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .context(format!("Invalid annotations file {}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).context(format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .context(format!("Failed to write {}", path.display()))
+    }
+
+    pub fn get(&self, dataset_slug: &str, key_name: &str) -> Option<&ColumnTags> {
+        self.datasets.get(dataset_slug)?.get(key_name)
+    }
+
+    pub fn set(&mut self, dataset_slug: &str, key_name: &str, tags: ColumnTags) {
+        self.datasets
+            .entry(dataset_slug.to_string())
+            .or_default()
+            .insert(key_name.to_string(), tags);
+    }
+
+    pub fn remove(&mut self, dataset_slug: &str, key_name: &str) -> Option<ColumnTags> {
+        let columns = self.datasets.get_mut(dataset_slug)?;
+        let tags = columns.remove(key_name);
+        if columns.is_empty() {
+            self.datasets.remove(dataset_slug);
+        }
+        tags
+    }
+
+    /// Join the tags onto a dataset's columns. Untagged columns get default tags.
+    pub fn annotate(&self, dataset_slug: &str, columns: &[Column]) -> Vec<AnnotatedColumn> {
+        columns
+            .iter()
+            .map(|c| AnnotatedColumn {
+                column: c.clone(),
+                tags: self
+                    .get(dataset_slug, &c.key_name)
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,540 @@
+use std::{collections::BTreeMap, future::Future, time::Duration};
+
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    dataset::DatasetFilter,
+    honeycomb::{Column, ColumnType, HoneyComb, QUERIES_ACCESS},
+    query::{Calculation, QueryOutput, QuerySpec, TimeRange},
+    report::{BulkReport, ReportSection},
+};
+
+/// Honeycomb's limit on the number of columns in a dataset.
+pub const MAX_COLUMNS_PER_DATASET: usize = 10_000;
+
+/// How many of a column's most common values are checked for type mismatches.
+const TYPE_SAMPLE_SIZE: usize = 20;
+
+/// A column that received no events in the audited window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnusedColumn {
+    pub key_name: String,
+    pub last_written: Option<DateTime<Utc>>,
+    /// The exists-count query that found no events, to link from a cleanup ticket.
+    pub evidence_url: String,
+}
+
+/// Unused columns by dataset.
+#[derive(Debug, Default)]
+pub struct UnusedColumnReport {
+    pub unused: BTreeMap<String, Vec<UnusedColumn>>,
+    /// How many columns were confirmed by query, used or not.
+    pub queried: usize,
+    /// Datasets whose columns couldn't be listed, or `dataset/key_name` for columns whose
+    /// query failed.
+    pub failed: Vec<(String, anyhow::Error)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColumnCardinality {
+    pub dataset: String,
+    pub key_name: String,
+    /// The estimated number of distinct values.
+    pub distinct: u64,
+    /// At or above the report's threshold.
+    pub high: bool,
+}
+
+/// Columns by estimated cardinality, highest first.
+#[derive(Debug, Default)]
+pub struct CardinalityReport {
+    pub threshold: u64,
+    pub columns: Vec<ColumnCardinality>,
+    /// Datasets whose columns couldn't be listed, or `dataset/key_name` for columns whose
+    /// query failed.
+    pub failed: Vec<(String, anyhow::Error)>,
+}
+
+impl CardinalityReport {
+    pub fn high(&self) -> impl Iterator<Item = &ColumnCardinality> {
+        self.columns.iter().filter(|c| c.high)
+    }
+}
+
+/// A string column whose values all look like another type, e.g. numbers sent as
+/// strings, which can't be used in numeric calculations.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TypeMismatch {
+    pub key_name: String,
+    pub declared: ColumnType,
+    pub observed: ColumnType,
+    /// The values that were checked.
+    pub sample: Vec<String>,
+}
+
+/// Type mismatches by dataset.
+#[derive(Debug, Default)]
+pub struct TypeMismatchReport {
+    pub mismatches: BTreeMap<String, Vec<TypeMismatch>>,
+    /// Datasets whose columns couldn't be listed, or `dataset/key_name` for columns whose
+    /// values couldn't be sampled.
+    pub failed: Vec<(String, anyhow::Error)>,
+}
+
+/// The narrowest type that every value parses as, if it isn't a plain string.
+fn observed_type(values: &[String]) -> Option<ColumnType> {
+    if values.is_empty() {
+        return None;
+    }
+    let all = |f: fn(&str) -> bool| values.iter().all(|v| f(v.trim()));
+    if all(|v| v == "true" || v == "false") {
+        Some(ColumnType::Boolean)
+    } else if all(|v| v.parse::<i64>().is_ok()) {
+        Some(ColumnType::Integer)
+    } else if all(|v| v.parse::<f64>().is_ok_and(f64::is_finite)) {
+        Some(ColumnType::Float)
+    } else {
+        None
+    }
+}
+
+/// The columns not written to for longer than a threshold.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StaleColumns {
+    pub older_than_days: i64,
+    pub columns: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DatasetFreshness {
+    pub slug: String,
+    pub last_written_at: Option<DateTime<Utc>>,
+    pub columns: usize,
+    pub never_written_columns: usize,
+    /// One entry per threshold, smallest first. Never-written columns aren't counted.
+    pub stale: Vec<StaleColumns>,
+}
+
+/// How recently datasets and their columns were written to.
+#[derive(Debug, Default)]
+pub struct FreshnessReport {
+    pub generated_at: DateTime<Utc>,
+    /// Sorted by slug.
+    pub datasets: Vec<DatasetFreshness>,
+    /// Datasets whose columns couldn't be listed.
+    pub failed: Vec<(String, anyhow::Error)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DailyVolume {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub events: u64,
+}
+
+/// A dataset's event count over a window, for attributing ingest costs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DatasetVolume {
+    pub dataset: String,
+    /// Earliest first. The last day may be partial.
+    pub days: Vec<DailyVolume>,
+    pub total: u64,
+    pub events_per_day: f64,
+}
+
+/// A dataset's column count against the column limit.
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnCount {
+    pub dataset: String,
+    /// Every column, which is what the limit counts.
+    pub columns: usize,
+    /// Columns written to within the report's active window.
+    pub active: usize,
+    /// At or above the report's warning fraction of [`MAX_COLUMNS_PER_DATASET`].
+    pub near_limit: bool,
+    /// The most recently created columns, newest first, as the likely cause of growth.
+    pub recent: Vec<Column>,
+}
+
+impl HoneyComb {
+    /// Run a report made of queries, or skip it if the key lacks query access rather than
+    /// failing every query with the same 403.
+    async fn query_report<T>(
+        &self,
+        report: impl Future<Output = T>,
+    ) -> anyhow::Result<ReportSection<T>> {
+        let auth = self.list_authorizations().await?;
+        ReportSection::run(&auth, &[QUERIES_ACCESS], async { Ok(report.await) }).await
+    }
+
+    /// Find columns in `datasets` that received no events in `time_range`. Columns
+    /// written to since the window started are in use and aren't queried; the rest are
+    /// confirmed with an exists-count query each, several at once, and reported with
+    /// the query URL as evidence. Skipped if the key lacks query access.
+    pub async fn audit_unused_columns(
+        &self,
+        datasets: &[String],
+        time_range: TimeRange,
+    ) -> anyhow::Result<ReportSection<UnusedColumnReport>> {
+        self.query_report(self.unused_columns(datasets, time_range))
+            .await
+    }
+
+    async fn unused_columns(
+        &self,
+        datasets: &[String],
+        time_range: TimeRange,
+    ) -> UnusedColumnReport {
+        let now = self.now();
+        let window_start = match time_range {
+            TimeRange::Absolute { start, .. } => start,
+            range => now - chrono::Duration::seconds(range.seconds() as i64),
+        };
+
+        let mut report = UnusedColumnReport::default();
+        let mut candidates = Vec::new();
+        for dataset_slug in datasets {
+            match self.list_all_columns(dataset_slug).await {
+                Ok(columns) => candidates.extend(
+                    columns
+                        .into_iter()
+                        .filter(|c| c.last_written.is_none_or(|w| w < window_start))
+                        .map(|c| (dataset_slug, c)),
+                ),
+                Err(e) => report.failed.push((dataset_slug.clone(), e)),
+            }
+        }
+
+        let mut tasks = stream::iter(candidates)
+            .map(|(dataset_slug, column)| async move {
+                let count = async {
+                    let _permit = self.query_slot().await?;
+                    let handle = self
+                        .get_exists_query_url(dataset_slug, &column.key_name, time_range, true)
+                        .await?;
+                    let output: QueryOutput<Map<String, Value>> =
+                        self.wait_for_results(&handle).await?;
+                    let count = output
+                        .results
+                        .iter()
+                        .filter_map(|row| row.get("COUNT").and_then(Value::as_u64))
+                        .sum::<u64>();
+                    Ok::<_, anyhow::Error>((count, handle.url))
+                };
+                let count = count.await;
+                (dataset_slug, column, count)
+            })
+            .buffer_unordered(self.query_concurrency());
+        while let Some((dataset_slug, column, count)) = tasks.next().await {
+            match count {
+                Ok((count, evidence_url)) => {
+                    report.queried += 1;
+                    if count == 0 {
+                        report
+                            .unused
+                            .entry(dataset_slug.clone())
+                            .or_default()
+                            .push(UnusedColumn {
+                                key_name: column.key_name,
+                                last_written: column.last_written,
+                                evidence_url,
+                            });
+                    }
+                }
+                Err(e) => report
+                    .failed
+                    .push((format!("{}/{}", dataset_slug, column.key_name), e)),
+            }
+        }
+        for columns in report.unused.values_mut() {
+            columns.sort_by(|a, b| a.key_name.cmp(&b.key_name));
+        }
+        report
+    }
+
+    /// Estimate the cardinality of every column in `datasets` with a `COUNT_DISTINCT`
+    /// query each, flagging those with at least `threshold` values. Queries run as many
+    /// at once as the client's adaptive limiter allows, if it has one. Skipped if the key
+    /// lacks query access.
+    pub async fn cardinality_report(
+        &self,
+        datasets: &[String],
+        time_range: TimeRange,
+        threshold: u64,
+    ) -> anyhow::Result<ReportSection<CardinalityReport>> {
+        self.query_report(self.column_cardinalities(datasets, time_range, threshold))
+            .await
+    }
+
+    async fn column_cardinalities(
+        &self,
+        datasets: &[String],
+        time_range: TimeRange,
+        threshold: u64,
+    ) -> CardinalityReport {
+        let mut report = CardinalityReport {
+            threshold,
+            ..Default::default()
+        };
+        let mut columns = Vec::new();
+        for dataset_slug in datasets {
+            match self.list_all_columns(dataset_slug).await {
+                Ok(c) => columns.extend(c.into_iter().map(|c| (dataset_slug, c.key_name))),
+                Err(e) => report.failed.push((dataset_slug.clone(), e)),
+            }
+        }
+
+        let mut tasks = stream::iter(columns)
+            .map(|(dataset_slug, key_name)| async move {
+                let distinct = self
+                    .get_cardinality(dataset_slug, &key_name, time_range)
+                    .await;
+                (dataset_slug, key_name, distinct)
+            })
+            .buffer_unordered(self.query_concurrency());
+        while let Some((dataset_slug, key_name, distinct)) = tasks.next().await {
+            match distinct {
+                Ok(distinct) => report.columns.push(ColumnCardinality {
+                    dataset: dataset_slug.clone(),
+                    key_name,
+                    distinct,
+                    high: distinct >= threshold,
+                }),
+                Err(e) => report
+                    .failed
+                    .push((format!("{}/{}", dataset_slug, key_name), e)),
+            }
+        }
+        report.columns.sort_by(|a, b| {
+            b.distinct
+                .cmp(&a.distinct)
+                .then_with(|| (&a.dataset, &a.key_name).cmp(&(&b.dataset, &b.key_name)))
+        });
+        report
+    }
+
+    /// Find string columns in `datasets` whose most common values in the last day are
+    /// all numbers or booleans. Numeric and boolean columns coerce values on ingest, so
+    /// only string columns are checked, with a small breakdown query each. Skipped if the
+    /// key lacks query access.
+    pub async fn detect_type_mismatches(
+        &self,
+        datasets: &[String],
+    ) -> anyhow::Result<ReportSection<TypeMismatchReport>> {
+        self.query_report(self.type_mismatches(datasets)).await
+    }
+
+    async fn type_mismatches(&self, datasets: &[String]) -> TypeMismatchReport {
+        let mut report = TypeMismatchReport::default();
+        let mut columns = Vec::new();
+        for dataset_slug in datasets {
+            match self.list_all_columns(dataset_slug).await {
+                Ok(c) => columns.extend(
+                    c.into_iter()
+                        .filter(|c| c.r#type == ColumnType::String)
+                        .map(|c| (dataset_slug, c.key_name)),
+                ),
+                Err(e) => report.failed.push((dataset_slug.clone(), e)),
+            }
+        }
+
+        let mut tasks = stream::iter(columns)
+            .map(|(dataset_slug, key_name)| async move {
+                let sample = self
+                    .sample_values(dataset_slug, &key_name, TYPE_SAMPLE_SIZE)
+                    .await;
+                (dataset_slug, key_name, sample)
+            })
+            .buffer_unordered(self.query_concurrency());
+        while let Some((dataset_slug, key_name, sample)) = tasks.next().await {
+            match sample {
+                Ok(sample) => {
+                    if let Some(observed) = observed_type(&sample) {
+                        report
+                            .mismatches
+                            .entry(dataset_slug.clone())
+                            .or_default()
+                            .push(TypeMismatch {
+                                key_name,
+                                declared: ColumnType::String,
+                                observed,
+                                sample,
+                            });
+                    }
+                }
+                Err(e) => report
+                    .failed
+                    .push((format!("{}/{}", dataset_slug, key_name), e)),
+            }
+        }
+        for mismatches in report.mismatches.values_mut() {
+            mismatches.sort_by(|a, b| a.key_name.cmp(&b.key_name));
+        }
+        report
+    }
+
+    /// For every dataset matching `filter`, its last write and how many of its columns
+    /// haven't been written to for longer than each of `stale_days`, e.g. `[30, 60, 90]`.
+    pub async fn freshness_report(
+        &self,
+        filter: &DatasetFilter,
+        stale_days: &[i64],
+    ) -> anyhow::Result<FreshnessReport> {
+        let now = self.now();
+        let mut thresholds = stale_days.to_vec();
+        thresholds.sort_unstable();
+        thresholds.dedup();
+        let datasets = self
+            .list_all_datasets()
+            .await?
+            .into_iter()
+            .filter(|d| filter.matches(&d.slug));
+
+        let mut report = FreshnessReport {
+            generated_at: now,
+            ..Default::default()
+        };
+        let mut tasks = stream::iter(datasets)
+            .map(|dataset| async move {
+                let columns = self.list_all_columns(&dataset.slug).await;
+                (dataset, columns)
+            })
+            .buffer_unordered(5);
+        while let Some((dataset, columns)) = tasks.next().await {
+            let columns = match columns {
+                Ok(columns) => columns,
+                Err(e) => {
+                    report.failed.push((dataset.slug, e));
+                    continue;
+                }
+            };
+            let ages = columns
+                .iter()
+                .filter_map(|c| Some((now - c.last_written?).num_days()))
+                .collect::<Vec<_>>();
+            report.datasets.push(DatasetFreshness {
+                slug: dataset.slug,
+                last_written_at: dataset.last_written_at,
+                columns: columns.len(),
+                never_written_columns: columns.len() - ages.len(),
+                stale: thresholds
+                    .iter()
+                    .map(|&days| StaleColumns {
+                        older_than_days: days,
+                        columns: ages.iter().filter(|&&age| age > days).count(),
+                    })
+                    .collect(),
+            });
+        }
+        report.datasets.sort_by(|a, b| a.slug.cmp(&b.slug));
+        Ok(report)
+    }
+
+    /// Count the events in each dataset over `time_range`, one query per day, to estimate
+    /// events per day. Counts are adjusted for sampling by the API. Skipped if the key
+    /// lacks query access.
+    pub async fn estimate_event_volume(
+        &self,
+        datasets: &[String],
+        time_range: TimeRange,
+    ) -> anyhow::Result<ReportSection<BulkReport<DatasetVolume>>> {
+        self.query_report(self.dataset_volumes(datasets, time_range))
+            .await
+    }
+
+    async fn dataset_volumes(
+        &self,
+        datasets: &[String],
+        time_range: TimeRange,
+    ) -> BulkReport<DatasetVolume> {
+        let spec = QuerySpec::builder().calc(Calculation::count()).build();
+        let day = Duration::from_secs(86400);
+        let mut report = BulkReport::default();
+        for dataset_slug in datasets {
+            let volume = self
+                .run_query_chunked::<Map<String, Value>>(
+                    dataset_slug,
+                    &spec,
+                    time_range,
+                    day,
+                    self.query_concurrency(),
+                )
+                .await
+                .map(|output| {
+                    let days = output
+                        .chunks
+                        .iter()
+                        .map(|chunk| DailyVolume {
+                            start: chunk.start,
+                            end: chunk.end,
+                            events: chunk
+                                .output
+                                .results
+                                .iter()
+                                .filter_map(|row| row.get("COUNT").and_then(Value::as_u64))
+                                .sum(),
+                        })
+                        .collect::<Vec<_>>();
+                    let total = days.iter().map(|d| d.events).sum::<u64>();
+                    let seconds = days
+                        .iter()
+                        .map(|d| (d.end - d.start).num_seconds())
+                        .sum::<i64>();
+                    DatasetVolume {
+                        dataset: dataset_slug.clone(),
+                        days,
+                        total,
+                        events_per_day: match seconds {
+                            0 => 0.0,
+                            seconds => total as f64 * 86400.0 / seconds as f64,
+                        },
+                    }
+                });
+            report.record(dataset_slug.clone(), volume);
+        }
+        report
+    }
+
+    /// Count the columns in each dataset, flagging datasets with at least `warn_at`
+    /// (e.g. `0.8`) of the column limit and listing their `recent` newest columns.
+    pub async fn column_counts(
+        &self,
+        datasets: &[String],
+        active_days: i64,
+        warn_at: f64,
+        recent: usize,
+    ) -> BulkReport<ColumnCount> {
+        let now = self.now();
+        let mut report = BulkReport::default();
+        let mut tasks =
+            stream::iter(datasets)
+                .map(|dataset_slug| async move {
+                    (dataset_slug, self.list_all_columns(dataset_slug).await)
+                })
+                .buffered(5);
+        while let Some((dataset_slug, columns)) = tasks.next().await {
+            let count = columns.map(|mut columns| {
+                let active = columns
+                    .iter()
+                    .filter(|c| {
+                        c.last_written
+                            .is_some_and(|w| (now - w).num_days() < active_days)
+                    })
+                    .count();
+                columns.sort_by_key(|c| std::cmp::Reverse(c.created_at));
+                ColumnCount {
+                    dataset: dataset_slug.clone(),
+                    columns: columns.len(),
+                    active,
+                    near_limit: columns.len() as f64 >= MAX_COLUMNS_PER_DATASET as f64 * warn_at,
+                    recent: columns.into_iter().take(recent).collect(),
+                }
+            });
+            report.record(dataset_slug.clone(), count);
+        }
+        report
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{api_enum::api_enum, honeycomb::HoneyComb};
+
+api_enum! {
+    pub enum ChartType {
+        Default => "default",
+        Line => "line",
+        Stacked => "stacked",
+        Stat => "stat",
+        TimeSeriesBar => "tsbar",
+        CategoricalBar => "cbar",
+        CategoricalPie => "cpie",
+    }
+}
+
+/// How a board query is drawn. Every setting is written out explicitly so a board
+/// exported from one environment looks the same after import into another.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GraphSettings {
+    #[serde(default)]
+    pub hide_markers: bool,
+    #[serde(default)]
+    pub log_scale: bool,
+    #[serde(default)]
+    pub omit_missing_values: bool,
+    /// Draw every calculation on one chart instead of one chart each.
+    #[serde(default)]
+    pub overlaid_charts: bool,
+    #[serde(default)]
+    pub stacked_graphs: bool,
+    #[serde(default)]
+    pub utc_xaxis: bool,
+    /// Settings not modelled here, kept so they survive a round trip.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoardQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    /// `graph`, `table` or `combo`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_style: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chart_type: Option<ChartType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graph_settings: Option<GraphSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_annotation_id: Option<String>,
+    /// Fields not modelled here, kept so they survive a round trip.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Board {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column_layout: Option<String>,
+    #[serde(default)]
+    pub queries: Vec<BoardQuery>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl HoneyComb {
+    pub async fn list_boards(&self) -> anyhow::Result<Vec<Board>> {
+        self.get("boards").await
+    }
+
+    pub async fn get_board(&self, board_id: &str) -> anyhow::Result<Board> {
+        self.get(&format!("boards/{}", board_id)).await
+    }
+}
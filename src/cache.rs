@@ -0,0 +1,162 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::query::QuerySpec;
+
+/// A stored query result in the API's shape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub stored_at: DateTime<Utc>,
+    pub body: Value,
+}
+
+/// Where cached query results are kept.
+pub trait CacheBackend: Debug + Send + Sync {
+    fn load(&self, key: &str) -> Option<CacheEntry>;
+    fn store(&self, key: &str, entry: CacheEntry);
+}
+
+/// Results kept in memory, shared by clones of the client. Entries older than `ttl` are
+/// dropped whenever a result is stored, so long-running tools don't keep every result.
+#[derive(Debug)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    ttl: Duration,
+}
+
+impl MemoryCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+}
+
+impl CacheBackend for MemoryCache {
+    fn load(&self, key: &str) -> Option<CacheEntry> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    fn store(&self, key: &str, entry: CacheEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, e| {
+            let age = (entry.stored_at - e.stored_at).to_std().unwrap_or_default();
+            age < self.ttl
+        });
+        entries.insert(key.to_string(), entry);
+    }
+}
+
+/// Results kept as one JSON file per key, so repeated runs of a tool can share them.
+/// Unreadable files are treated as misses and write failures are ignored.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+impl CacheBackend for DiskCache {
+    fn load(&self, key: &str) -> Option<CacheEntry> {
+        let text = fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    fn store(&self, key: &str, entry: CacheEntry) {
+        if fs::create_dir_all(&self.dir).is_ok() {
+            if let Ok(json) = serde_json::to_vec(&entry) {
+                let _ = fs::write(self.path(key), json);
+            }
+        }
+    }
+}
+
+/// Caches query results keyed on dataset, the spec hash and a time bucket, so identical
+/// queries within a bucket are answered without using the rate limit.
+#[derive(Debug, Clone)]
+pub struct QueryCache {
+    backend: Arc<dyn CacheBackend>,
+    ttl: Duration,
+    bucket: Duration,
+}
+
+impl QueryCache {
+    /// Entries expire after `ttl`, which is also the width of the time bucket.
+    pub fn new(backend: impl CacheBackend + 'static, ttl: Duration) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            ttl,
+            bucket: ttl,
+        }
+    }
+
+    pub fn memory(ttl: Duration) -> Self {
+        Self::new(MemoryCache::new(ttl), ttl)
+    }
+
+    pub fn disk(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self::new(DiskCache::new(dir), ttl)
+    }
+
+    /// Relative time ranges cover different data as time passes, so keys change every
+    /// `bucket` even when the spec doesn't.
+    pub fn with_bucket(mut self, bucket: Duration) -> Self {
+        self.bucket = bucket;
+        self
+    }
+
+    /// `scope` identifies where and how the query runs, e.g. a hash of the API host, the
+    /// team and environment and the result limit, so clients sharing a backend only see
+    /// each other's results when they would have got the same ones.
+    pub fn key(
+        &self,
+        scope: u64,
+        dataset_slug: &str,
+        spec: &QuerySpec,
+        now: DateTime<Utc>,
+    ) -> String {
+        let bucket = now.timestamp() / self.bucket.as_secs().max(1) as i64;
+        format!(
+            "{}-{:016x}-{:016x}-{}",
+            dataset_slug,
+            scope,
+            spec.spec_hash(),
+            bucket
+        )
+    }
+
+    pub(crate) fn get(&self, key: &str, now: DateTime<Utc>) -> Option<Value> {
+        let entry = self.backend.load(key)?;
+        let age = (now - entry.stored_at).to_std().unwrap_or_default();
+        (age < self.ttl).then_some(entry.body)
+    }
+
+    pub(crate) fn put(&self, key: &str, now: DateTime<Utc>, body: Value) {
+        self.backend.store(
+            key,
+            CacheEntry {
+                stored_at: now,
+                body,
+            },
+        );
+    }
+}
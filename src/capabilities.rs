@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{
+    error::{ApiError, KeyKind},
+    honeycomb::{HoneyComb, QUERIES_ACCESS},
+};
+
+/// The version of the Honeycomb API this crate is written against.
+pub const API_VERSION: &str = "1";
+
+/// Features that depend on the account or key rather than on this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// The team uses environments rather than Honeycomb Classic.
+    Environments,
+    /// Queries across every dataset in an environment with the `__all__` slug.
+    EnvironmentQueries,
+    /// Endpoints under `/1/` accept the key. Management keys only work with `/2/`.
+    V1Api,
+}
+
+/// What the account and key support, detected by probing the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    pub api_version: &'static str,
+    pub key_kind: KeyKind,
+    pub environments: bool,
+    pub environment_queries: bool,
+    pub api_key_access: HashMap<String, bool>,
+}
+
+impl Capabilities {
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Environments => self.environments,
+            Capability::EnvironmentQueries => self.environment_queries,
+            Capability::V1Api => self.key_kind != KeyKind::Management,
+        }
+    }
+
+    /// Fail with a helpful error if the capability is unsupported.
+    pub fn require(&self, capability: Capability) -> anyhow::Result<()> {
+        if self.supports(capability) {
+            return Ok(());
+        }
+        let reason = match capability {
+            Capability::Environments | Capability::EnvironmentQueries => {
+                "this is a Honeycomb Classic team; environment features need an environment"
+            }
+            Capability::V1Api => "management keys are only accepted by the v2 API",
+        };
+        Err(ApiError::Unsupported { capability, reason }.into())
+    }
+}
+
+impl HoneyComb {
+    /// Detect the capabilities of the account and key, or of the key pool's keys if the
+    /// client has one. The probe runs once per client (and its clones) and the result is
+    /// cached.
+    pub async fn capabilities(&self) -> anyhow::Result<Capabilities> {
+        let capabilities = self
+            .capabilities
+            .get_or_try_init(|| async {
+                let key_kind = match &self.key_pool {
+                    Some(key_pool) => key_pool.key_kind(),
+                    None => KeyKind::of(&self.api_key),
+                };
+                if key_kind == KeyKind::Management {
+                    return Ok::<_, anyhow::Error>(Capabilities {
+                        api_version: API_VERSION,
+                        key_kind,
+                        environments: true,
+                        environment_queries: false,
+                        api_key_access: HashMap::new(),
+                    });
+                }
+                let auth = self.list_authorizations().await?;
+                // Classic teams report an environment with an empty slug
+                let environments = !auth.environment.slug.is_empty();
+                Ok(Capabilities {
+                    api_version: API_VERSION,
+                    key_kind,
+                    environments,
+                    environment_queries: environments
+                        && *auth.api_key_access.get(QUERIES_ACCESS).unwrap_or(&false),
+                    api_key_access: auth.api_key_access,
+                })
+            })
+            .await?;
+        Ok(capabilities.clone())
+    }
+
+    /// Fail with a helpful error if the account or key doesn't support the capability.
+    pub async fn require_capability(&self, capability: Capability) -> anyhow::Result<()> {
+        self.capabilities().await?.require(capability)
+    }
+}
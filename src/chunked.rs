@@ -0,0 +1,266 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde_json::{Map, Value};
+
+use crate::{
+    honeycomb::HoneyComb,
+    query::{CalcOp, QueryOutput, QuerySpec, TimeRange},
+};
+
+/// The results of one window of a chunked query.
+#[derive(Debug, Clone)]
+pub struct Chunk<T> {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub output: QueryOutput<T>,
+}
+
+/// A query over a long range, run as consecutive windows, earliest first.
+#[derive(Debug, Clone)]
+pub struct ChunkedOutput<T> {
+    pub spec: QuerySpec,
+    pub chunks: Vec<Chunk<T>>,
+}
+
+impl TimeRange {
+    /// Split the range ending at `now` (for relative ranges) into consecutive windows of
+    /// at most `chunk`.
+    pub fn windows(
+        &self,
+        now: DateTime<Utc>,
+        chunk: Duration,
+    ) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let (start, end) = match self {
+            Self::Absolute { start, end } => (*start, *end),
+            relative => (
+                now - chrono::Duration::seconds(relative.seconds() as i64),
+                now,
+            ),
+        };
+        let step = chrono::Duration::from_std(chunk)
+            .unwrap_or(chrono::Duration::MAX)
+            .max(chrono::Duration::seconds(1));
+        let mut windows = Vec::new();
+        let mut window_start = start;
+        while window_start < end {
+            // A chunk longer than the dates chrono can represent is one window
+            let window_end = window_start
+                .checked_add_signed(step)
+                .map_or(end, |t| t.min(end));
+            windows.push((window_start, window_end));
+            window_start = window_end;
+        }
+        windows
+    }
+}
+
+impl<T> ChunkedOutput<T> {
+    /// Every row of every window, earliest window first.
+    pub fn rows(&self) -> impl Iterator<Item = &T> {
+        self.chunks.iter().flat_map(|c| c.output.results.iter())
+    }
+}
+
+impl ChunkedOutput<Map<String, Value>> {
+    /// Combine the windows into one row per breakdown group, as if the query had run over
+    /// the whole range. Only `COUNT`, `SUM`, `MIN` and `MAX` can be combined this way;
+    /// other calculations are an error. So are specs with a limit, orders or havings,
+    /// since each window would be cut to its own top groups rather than the range's.
+    pub fn rollup(&self) -> anyhow::Result<Vec<Map<String, Value>>> {
+        anyhow::ensure!(
+            self.spec.limit.is_none()
+                && self.spec.orders.is_empty()
+                && self.spec.havings.is_empty(),
+            "queries with a limit, orders or havings can't be combined across windows"
+        );
+        let calculations = self
+            .spec
+            .calculations
+            .iter()
+            .map(|c| {
+                let key = match &c.column {
+                    Some(column) => format!("{}({})", c.op, column),
+                    None => c.op.to_string(),
+                };
+                match c.op {
+                    CalcOp::Count | CalcOp::Sum | CalcOp::Min | CalcOp::Max => Ok((key, &c.op)),
+                    _ => Err(anyhow::anyhow!("{} can't be combined across windows", key)),
+                }
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut groups: Vec<(Vec<Value>, Map<String, Value>)> = Vec::new();
+        for row in self.rows() {
+            let group = self
+                .spec
+                .breakdowns
+                .iter()
+                .map(|b| row.get(&b.0).cloned().unwrap_or_default())
+                .collect::<Vec<_>>();
+            let combined = match groups.iter_mut().find(|(g, _)| *g == group) {
+                Some((_, combined)) => combined,
+                None => {
+                    groups.push((group, row.clone()));
+                    continue;
+                }
+            };
+            for (key, op) in &calculations {
+                let (Some(a), Some(b)) = (
+                    combined.get(key).and_then(Value::as_f64),
+                    row.get(key).and_then(Value::as_f64),
+                ) else {
+                    continue;
+                };
+                let value = match op {
+                    CalcOp::Min => a.min(b),
+                    CalcOp::Max => a.max(b),
+                    _ => a + b,
+                };
+                let both_integers = combined[key].is_i64() && row[key].is_i64();
+                combined.insert(
+                    key.clone(),
+                    if both_integers {
+                        Value::from(value as i64)
+                    } else {
+                        Value::from(value)
+                    },
+                );
+            }
+        }
+        Ok(groups.into_iter().map(|(_, row)| row).collect())
+    }
+}
+
+impl HoneyComb {
+    /// Run a query over a range longer than the API allows by splitting it into windows
+    /// of at most `chunk`, running up to `parallelism` windows at once. Any failed window
+    /// fails the whole query since the results would be incomplete.
+    pub async fn run_query_chunked<T>(
+        &self,
+        dataset_slug: &str,
+        spec: &QuerySpec,
+        range: TimeRange,
+        chunk: Duration,
+        parallelism: usize,
+    ) -> anyhow::Result<ChunkedOutput<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let windows = range.windows(self.now(), chunk);
+        let chunks = stream::iter(windows)
+            .map(|(start, end)| async move {
+                let mut window = spec.clone();
+                window.time_range = None;
+                window.start_time = Some(start);
+                window.end_time = Some(end);
+                let output = self.run_query(dataset_slug, &window).await?;
+                Ok::<_, anyhow::Error>(Chunk { start, end, output })
+            })
+            .buffered(parallelism.max(1))
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(ChunkedOutput {
+            spec: spec.clone(),
+            chunks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::query::Calculation;
+
+    fn range(hours: u32) -> TimeRange {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        TimeRange::Absolute {
+            start,
+            end: start + chrono::Duration::hours(hours.into()),
+        }
+    }
+
+    fn hours(windows: &[(DateTime<Utc>, DateTime<Utc>)]) -> Vec<i64> {
+        windows.iter().map(|(s, e)| (*e - *s).num_hours()).collect()
+    }
+
+    #[test]
+    fn windows_divide_an_exact_multiple_evenly() {
+        let windows = range(72).windows(Utc::now(), Duration::from_secs(24 * 3600));
+        assert_eq!(hours(&windows), [24, 24, 24]);
+        assert!(windows.windows(2).all(|w| w[0].1 == w[1].0));
+    }
+
+    #[test]
+    fn windows_end_with_the_remainder() {
+        let windows = range(50).windows(Utc::now(), Duration::from_secs(24 * 3600));
+        assert_eq!(hours(&windows), [24, 24, 2]);
+    }
+
+    fn chunk(rows: Value) -> Chunk<Map<String, Value>> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        Chunk {
+            start,
+            end: start,
+            output: serde_json::from_value(serde_json::json!({
+                "complete": true,
+                "data": {"results": rows}
+            }))
+            .unwrap(),
+        }
+    }
+
+    fn spec() -> QuerySpec {
+        QuerySpec::builder()
+            .breakdown("service.name")
+            .calc(Calculation::count())
+            .calc(Calculation::max("duration_ms"))
+            .build()
+    }
+
+    #[test]
+    fn rollup_combines_groups_across_windows() {
+        let output = ChunkedOutput {
+            spec: spec(),
+            chunks: vec![
+                chunk(serde_json::json!([
+                    {"data": {"service.name": "api", "COUNT": 3, "MAX(duration_ms)": 12.5}},
+                    {"data": {"service.name": "web", "COUNT": 1, "MAX(duration_ms)": 4.0}},
+                ])),
+                chunk(serde_json::json!([
+                    {"data": {"service.name": "api", "COUNT": 2, "MAX(duration_ms)": 30.0}},
+                ])),
+            ],
+        };
+        let rows = output.rollup().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["COUNT"], 5);
+        assert_eq!(rows[0]["MAX(duration_ms)"], 30.0);
+        assert_eq!(rows[1]["COUNT"], 1);
+    }
+
+    #[test]
+    fn rollup_refuses_what_it_cant_combine() {
+        let mut limited = spec();
+        limited.limit = Some(10);
+        let averaged = QuerySpec::builder()
+            .calc(Calculation::avg("duration_ms"))
+            .build();
+        for spec in [limited, averaged] {
+            let output = ChunkedOutput {
+                spec,
+                chunks: vec![],
+            };
+            assert!(output.rollup().is_err());
+        }
+    }
+
+    #[test]
+    fn an_oversized_chunk_is_one_window() {
+        let windows = range(50).windows(Utc::now(), Duration::MAX);
+        assert_eq!(hours(&windows), [50]);
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::{RetriesExhausted, StatusError},
+    retry::is_transient,
+};
+
+/// Returned instead of sending a request while an endpoint's circuit is open.
+#[derive(Debug, Clone)]
+pub struct CircuitOpen {
+    pub endpoint: String,
+    pub retry_in: Duration,
+}
+
+impl Display for CircuitOpen {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "circuit open for {} after repeated failures, retry in {}s",
+            self.endpoint,
+            self.retry_in.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    /// A request has been let through after the cooldown and hasn't finished yet.
+    probing: bool,
+}
+
+/// Trips per endpoint after `failure_threshold` consecutive failed requests and then
+/// fails fast for `cooldown`. After the cooldown one request is let through while the
+/// rest keep failing fast: success closes the circuit, failure opens it again. Clones
+/// share the circuits.
+///
+/// Only errors that point at the endpoint count as failures: exhausted retries, 5xx
+/// responses and connection failures. Errors such as a 404 or an invalid query mean the
+/// endpoint answered, so they count as successes.
+///
+/// Endpoints are the first two path segments of a request, e.g. `query_results/{dataset}`,
+/// so one failing dataset doesn't block the others.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    circuits: Arc<Mutex<HashMap<String, Circuit>>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            circuits: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub(crate) fn endpoint(request: &str) -> String {
+        request
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .take(2)
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Admit a request to `endpoint` unless its circuit is open. After the cooldown only
+    /// one request at a time is admitted, as the probe.
+    pub(crate) fn check(&self, endpoint: &str) -> Result<Admission<'_>, CircuitOpen> {
+        let mut circuits = self.circuits.lock().unwrap();
+        let admission = |probe| Admission {
+            breaker: self,
+            endpoint: endpoint.to_string(),
+            probe,
+        };
+        let Some(circuit) = circuits.get_mut(endpoint) else {
+            return Ok(admission(false));
+        };
+        let now = Instant::now();
+        match circuit.open_until {
+            Some(until) if until > now || circuit.probing => Err(CircuitOpen {
+                endpoint: endpoint.to_string(),
+                retry_in: until.saturating_duration_since(now),
+            }),
+            Some(_) => {
+                circuit.probing = true;
+                Ok(admission(true))
+            }
+            None => Ok(admission(false)),
+        }
+    }
+
+    fn record(&self, endpoint: &str, success: bool) {
+        let mut circuits = self.circuits.lock().unwrap();
+        if success {
+            circuits.remove(endpoint);
+            return;
+        }
+        let circuit = circuits.entry(endpoint.to_string()).or_default();
+        circuit.consecutive_failures += 1;
+        circuit.probing = false;
+        if circuit.consecutive_failures >= self.failure_threshold {
+            circuit.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+
+    /// Whether an error suggests the endpoint itself is failing, rather than the request.
+    pub(crate) fn is_failure(e: &anyhow::Error) -> bool {
+        e.is::<RetriesExhausted>()
+            || e.downcast_ref::<StatusError>()
+                .is_some_and(|e| e.status >= 500)
+            || e.downcast_ref::<reqwest::Error>().is_some_and(is_transient)
+    }
+
+    /// Endpoints whose circuit is currently open.
+    pub fn open_endpoints(&self) -> Vec<String> {
+        let now = Instant::now();
+        self.circuits
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, c)| c.open_until.is_some_and(|until| until > now))
+            .map(|(endpoint, _)| endpoint.clone())
+            .collect()
+    }
+}
+
+/// A request admitted by [`CircuitBreaker::check`]. If it was the probe and is dropped
+/// without an outcome, e.g. because it was cancelled or its future was dropped by a
+/// timeout, the next request can probe instead.
+pub(crate) struct Admission<'a> {
+    breaker: &'a CircuitBreaker,
+    endpoint: String,
+    probe: bool,
+}
+
+impl Admission<'_> {
+    pub(crate) fn record(mut self, success: bool) {
+        self.probe = false;
+        self.breaker.record(&self.endpoint, success);
+    }
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        if !self.probe {
+            return;
+        }
+        if let Some(circuit) = self
+            .breaker
+            .circuits
+            .lock()
+            .unwrap()
+            .get_mut(&self.endpoint)
+        {
+            circuit.probing = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_dropped_probe_lets_the_next_request_probe() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        breaker.check("datasets").unwrap().record(false);
+        let probe = breaker.check("datasets").unwrap();
+        assert!(breaker.check("datasets").is_err());
+        drop(probe);
+        breaker.check("datasets").unwrap().record(true);
+        assert!(breaker.open_endpoints().is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use serde::Serialize;
+
+use crate::{
+    honeycomb::{Column, ColumnType, HoneyComb},
+    report::BulkReport,
+};
+
+/// Changes to a column; fields left as `None` are unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ColumnUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<ColumnType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+}
+
+/// What a bulk column operation does to each selected column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnAction {
+    Hide,
+    Delete,
+}
+
+/// Select columns by key name in any dataset, for [`HoneyComb::bulk_column_action`].
+pub fn key_names(names: &[&str]) -> impl Fn(&str, &Column) -> bool {
+    let names = names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+    move |_, column| names.contains(&column.key_name)
+}
+
+/// The steps of moving from an old column to its replacement, e.g. when adopting a new
+/// semantic convention.
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnRename {
+    pub old: Column,
+    pub new: Column,
+    /// The new column has no description so it gets the old one's.
+    pub copy_description: bool,
+    /// The old column isn't hidden yet.
+    pub hide_old: bool,
+    /// False for a dry run.
+    pub applied: bool,
+}
+
+/// Encode `key=value` pairs for a query string.
+fn query_string(pairs: &[(&str, &str)]) -> String {
+    let mut url = reqwest::Url::parse("http://localhost/").expect("valid URL");
+    url.query_pairs_mut().extend_pairs(pairs);
+    url.query().unwrap_or_default().to_string()
+}
+
+impl HoneyComb {
+    /// Look up a column by name rather than ID, without listing every column.
+    pub async fn get_column_by_key(
+        &self,
+        dataset_slug: &str,
+        key_name: &str,
+    ) -> anyhow::Result<Column> {
+        self.get(&format!(
+            "columns/{}?{}",
+            dataset_slug,
+            query_string(&[("key_name", key_name)])
+        ))
+        .await
+    }
+
+    /// Create a column ahead of instrumentation sending it, so its type and description
+    /// are set from the start.
+    pub async fn create_column(
+        &self,
+        dataset_slug: &str,
+        key_name: &str,
+        r#type: ColumnType,
+        description: Option<&str>,
+        hidden: bool,
+    ) -> anyhow::Result<Column> {
+        let mut body = serde_json::json!({
+            "key_name": key_name,
+            "type": r#type,
+            "hidden": hidden,
+        });
+        if let Some(description) = description {
+            body["description"] = description.into();
+        }
+        self.post(&format!("columns/{}", dataset_slug), body).await
+    }
+
+    pub async fn update_column(
+        &self,
+        dataset_slug: &str,
+        column_id: &str,
+        changes: &ColumnUpdate,
+    ) -> anyhow::Result<Column> {
+        self.put(
+            &format!("columns/{}/{}", dataset_slug, column_id),
+            serde_json::to_value(changes)?,
+        )
+        .await
+    }
+
+    /// Delete a column and its data from every event in the dataset. A column ID or dataset
+    /// that doesn't exist fails with a [`crate::error::StatusError`]
+    /// for which `is_not_found()` is true.
+    pub async fn delete_column(&self, dataset_slug: &str, column_id: &str) -> anyhow::Result<()> {
+        self.delete(&format!("columns/{}/{}", dataset_slug, column_id))
+            .await
+    }
+
+    /// Hide or delete every column in `datasets` for which `select(dataset, column)` is
+    /// true, several at a time. Items are `dataset/key_name`, or the dataset slug if its
+    /// columns couldn't be listed. With `dry_run` nothing changes and selected columns are
+    /// reported as skipped, as are columns that are already hidden. A column that is gone
+    /// by the time it is deleted is reported as failed.
+    pub async fn bulk_column_action<F>(
+        &self,
+        datasets: &[String],
+        select: F,
+        action: ColumnAction,
+        dry_run: bool,
+    ) -> anyhow::Result<BulkReport<(String, Column)>>
+    where
+        F: Fn(&str, &Column) -> bool,
+    {
+        let mut report = BulkReport::default();
+        let mut selected = Vec::new();
+        let mut listings =
+            stream::iter(datasets)
+                .map(|dataset_slug| async move {
+                    (dataset_slug, self.list_all_columns(dataset_slug).await)
+                })
+                .buffered(5);
+        while let Some((dataset_slug, columns)) = listings.next().await {
+            let columns = match columns {
+                Ok(columns) => columns,
+                Err(e) => {
+                    report.record(dataset_slug.clone(), Err(e));
+                    continue;
+                }
+            };
+            for column in columns.into_iter().filter(|c| select(dataset_slug, c)) {
+                let item = format!("{}/{}", dataset_slug, column.key_name);
+                if action == ColumnAction::Hide && column.hidden {
+                    report.skipped.push((item, "already hidden".to_string()));
+                } else if dry_run {
+                    report
+                        .skipped
+                        .push((item, format!("dry run, would {:?}", action).to_lowercase()));
+                } else {
+                    selected.push((item, dataset_slug, column));
+                }
+            }
+        }
+
+        let mut tasks = stream::iter(selected)
+            .map(|(item, dataset_slug, column)| async move {
+                let result = match action {
+                    ColumnAction::Hide => {
+                        self.update_column(
+                            dataset_slug,
+                            &column.id,
+                            &ColumnUpdate {
+                                hidden: Some(true),
+                                ..Default::default()
+                            },
+                        )
+                        .await
+                    }
+                    ColumnAction::Delete => self
+                        .delete_column(dataset_slug, &column.id)
+                        .await
+                        .map(|()| column),
+                };
+                (item, result.map(|column| (dataset_slug.clone(), column)))
+            })
+            .buffer_unordered(5);
+        while let Some((item, result)) = tasks.next().await {
+            report.record(item, result);
+        }
+        Ok(report)
+    }
+
+    /// Replace `old_key` with `new_key`: check the new column has been written to within
+    /// `max_age`, give it the old column's description if it has none, then hide the old
+    /// column. With `dry_run` the checks run but nothing changes.
+    pub async fn rename_column(
+        &self,
+        dataset_slug: &str,
+        old_key: &str,
+        new_key: &str,
+        max_age: Duration,
+        dry_run: bool,
+    ) -> anyhow::Result<ColumnRename> {
+        let old = self.get_column_by_key(dataset_slug, old_key).await?;
+        let new = self.get_column_by_key(dataset_slug, new_key).await?;
+        let max_age = chrono::Duration::from_std(max_age)?;
+        let receiving = new
+            .last_written
+            .is_some_and(|written| self.now() - written <= max_age);
+        anyhow::ensure!(
+            receiving,
+            "{} in {} hasn't been written to recently (last written {:?}), not hiding {}",
+            new_key,
+            dataset_slug,
+            new.last_written,
+            old_key
+        );
+
+        let mut rename = ColumnRename {
+            copy_description: new.description.is_empty() && !old.description.is_empty(),
+            hide_old: !old.hidden,
+            applied: !dry_run,
+            old,
+            new,
+        };
+        if dry_run {
+            return Ok(rename);
+        }
+        if rename.copy_description {
+            let changes = ColumnUpdate {
+                description: Some(rename.old.description.clone()),
+                ..Default::default()
+            };
+            rename.new = self
+                .update_column(dataset_slug, &rename.new.id, &changes)
+                .await?;
+        }
+        if rename.hide_old {
+            let changes = ColumnUpdate {
+                hidden: Some(true),
+                ..Default::default()
+            };
+            rename.old = self
+                .update_column(dataset_slug, &rename.old.id, &changes)
+                .await?;
+        }
+        Ok(rename)
+    }
+}
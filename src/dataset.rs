@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::DeleteProtected,
+    honeycomb::{Dataset, HoneyComb},
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatasetSettings {
+    /// Protected datasets can't be deleted until protection is turned off.
+    #[serde(default)]
+    pub delete_protected: bool,
+}
+
+/// Changes to a dataset's metadata; fields left as `None` are unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DatasetUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// How many levels of nested JSON objects are expanded into columns, up to 10.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expand_json_depth: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings: Option<DatasetSettings>,
+}
+
+/// Match `text` against a glob where `*` matches any run of characters and `?` any one.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// What to do with datasets that have never been written to, which have no
+/// `last_written_at` and so no age.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NeverWritten {
+    /// Treat them as just written, so they are always included.
+    #[default]
+    Include,
+    Exclude,
+    /// Leave them out of the active datasets and list them on their own, e.g. to find
+    /// abandoned datasets.
+    Separate,
+}
+
+/// Dataset slugs chosen by a [`DatasetFilter`], sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatasetSlugs {
+    pub active: Vec<String>,
+    /// Never-written datasets, with [`NeverWritten::Separate`].
+    pub never_written: Vec<String>,
+}
+
+/// Which datasets to include by slug, as glob patterns such as `checkout-*`. Plain slugs
+/// match exactly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatasetFilter {
+    /// Datasets must match one of these, unless it is empty.
+    pub include: Vec<String>,
+    /// Datasets matching any of these are left out, e.g. `*-staging`.
+    pub exclude: Vec<String>,
+    pub never_written: NeverWritten,
+}
+
+impl DatasetFilter {
+    pub fn include<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.include.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn exclude<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn never_written(mut self, never_written: NeverWritten) -> Self {
+        self.never_written = never_written;
+        self
+    }
+
+    pub fn matches(&self, dataset_slug: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| glob_match(p, dataset_slug)))
+            && !self.exclude.iter().any(|p| glob_match(p, dataset_slug))
+    }
+}
+
+impl HoneyComb {
+    pub async fn get_dataset(&self, dataset_slug: &str) -> anyhow::Result<Dataset> {
+        self.get(&format!("datasets/{}", dataset_slug)).await
+    }
+
+    /// Create a dataset ahead of sending data to it. Creating a dataset that already
+    /// exists returns the existing one.
+    pub async fn create_dataset(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        settings: &DatasetSettings,
+    ) -> anyhow::Result<Dataset> {
+        let mut body = serde_json::json!({
+            "name": name,
+            "settings": settings,
+        });
+        if let Some(description) = description {
+            body["description"] = description.into();
+        }
+        self.post("datasets", body).await
+    }
+
+    pub async fn update_dataset(
+        &self,
+        dataset_slug: &str,
+        changes: &DatasetUpdate,
+    ) -> anyhow::Result<Dataset> {
+        self.put(
+            &format!("datasets/{}", dataset_slug),
+            serde_json::to_value(changes)?,
+        )
+        .await
+    }
+
+    /// Delete a dataset and all its data. Fails with [`DeleteProtected`] if delete
+    /// protection is on, and with a not-found [`crate::error::StatusError`] if there is no
+    /// such dataset, e.g. because it was already deleted.
+    pub async fn delete_dataset(&self, dataset_slug: &str) -> anyhow::Result<()> {
+        self.delete(&format!("datasets/{}", dataset_slug)).await
+    }
+
+    /// Delete a dataset, first turning delete protection off if it is on and
+    /// `disable_protection` is set. Without it this is [`HoneyComb::delete_dataset`].
+    pub async fn delete_dataset_unprotected(
+        &self,
+        dataset_slug: &str,
+        disable_protection: bool,
+    ) -> anyhow::Result<()> {
+        match self.delete_dataset(dataset_slug).await {
+            Err(e) if disable_protection && e.is::<DeleteProtected>() => {
+                self.update_dataset(
+                    dataset_slug,
+                    &DatasetUpdate {
+                        settings: Some(DatasetSettings {
+                            delete_protected: false,
+                        }),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+                self.delete_dataset(dataset_slug).await
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stars_match_any_run_including_none() {
+        for (pattern, text) in [
+            ("*", ""),
+            ("*", "checkout"),
+            ("checkout*", "checkout"),
+            ("*-prod", "checkout-prod"),
+            ("*out*", "checkout-prod"),
+            ("c*t*d", "checkout-prod"),
+            ("**", "x"),
+        ] {
+            assert!(glob_match(pattern, text), "{} {}", pattern, text);
+        }
+        for (pattern, text) in [
+            ("checkout*", "cart"),
+            ("*-prod", "checkout-prod-2"),
+            ("c*x", "checkout"),
+            ("", "checkout"),
+        ] {
+            assert!(!glob_match(pattern, text), "{} {}", pattern, text);
+        }
+    }
+
+    #[test]
+    fn question_marks_match_exactly_one_character() {
+        assert!(glob_match("?heckout", "checkout"));
+        assert!(glob_match("checkou?", "checkout"));
+        assert!(glob_match("?*?", "ab"));
+        assert!(!glob_match("?", ""));
+        assert!(!glob_match("checkout?", "checkout"));
+        assert!(!glob_match("?checkout", "checkout"));
+    }
+}
@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    api_enum::api_enum,
+    honeycomb::{Column, ColumnType, HoneyComb},
+};
+
+api_enum! {
+    pub enum DefinitionColumnType {
+        Column => "column",
+        DerivedColumn => "derived_column",
+    }
+}
+
+/// The column, or derived column, a dataset definition maps to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DefinitionColumn {
+    pub name: String,
+    /// Set by the API, not needed when updating.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column_type: Option<DefinitionColumnType>,
+}
+
+impl DefinitionColumn {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            column_type: None,
+        }
+    }
+}
+
+/// Which columns Honeycomb uses for tracing and the UI's special fields. Definitions
+/// left as `None` are unset when read and unchanged when updating.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatasetDefinitions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_kind: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotation_type: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_trace_id: Option<DefinitionColumn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_span_id: Option<DefinitionColumn>,
+    /// Definitions not modelled above, kept so they round-trip.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// What is wrong with the column a definition maps to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum DefinitionProblem {
+    /// The column isn't in the dataset.
+    MissingColumn,
+    /// The column's type doesn't suit the definition, e.g. a string `duration_ms`.
+    WrongType {
+        expected: Vec<ColumnType>,
+        actual: ColumnType,
+    },
+    /// The column hasn't been written to recently, or ever.
+    Stale { last_written: Option<DateTime<Utc>> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DefinitionFinding {
+    /// The definition, e.g. `trace_id`.
+    pub definition: String,
+    pub column: String,
+    #[serde(flatten)]
+    pub problem: DefinitionProblem,
+}
+
+impl DatasetDefinitions {
+    /// The modelled definitions that are set, by name.
+    pub fn entries(&self) -> Vec<(&'static str, &DefinitionColumn)> {
+        [
+            ("trace_id", &self.trace_id),
+            ("span_id", &self.span_id),
+            ("parent_id", &self.parent_id),
+            ("name", &self.name),
+            ("service_name", &self.service_name),
+            ("duration_ms", &self.duration_ms),
+            ("error", &self.error),
+            ("status", &self.status),
+            ("route", &self.route),
+            ("user", &self.user),
+            ("span_kind", &self.span_kind),
+            ("annotation_type", &self.annotation_type),
+            ("link_trace_id", &self.link_trace_id),
+            ("link_span_id", &self.link_span_id),
+        ]
+        .into_iter()
+        .filter_map(|(name, column)| Some((name, column.as_ref()?)))
+        .collect()
+    }
+
+    /// Check every definition maps to a column that exists, has a suitable type and was
+    /// written to within `max_age` of `now`. Derived columns aren't returned by the
+    /// Columns API so definitions mapped to them aren't checked.
+    pub fn validate(
+        &self,
+        columns: &[Column],
+        now: DateTime<Utc>,
+        max_age: Duration,
+    ) -> Vec<DefinitionFinding> {
+        let oldest = chrono::Duration::from_std(max_age)
+            .ok()
+            .and_then(|age| now.checked_sub_signed(age))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let mut findings = Vec::new();
+        for (definition, mapped) in self.entries() {
+            if mapped.column_type == Some(DefinitionColumnType::DerivedColumn) {
+                continue;
+            }
+            let finding = |problem| DefinitionFinding {
+                definition: definition.to_string(),
+                column: mapped.name.clone(),
+                problem,
+            };
+            let Some(column) = columns.iter().find(|c| c.key_name == mapped.name) else {
+                findings.push(finding(DefinitionProblem::MissingColumn));
+                continue;
+            };
+            let expected = match definition {
+                "duration_ms" => vec![ColumnType::Float, ColumnType::Integer],
+                "error" => vec![ColumnType::Boolean, ColumnType::String],
+                "status" => vec![ColumnType::Integer, ColumnType::String],
+                _ => vec![ColumnType::String],
+            };
+            if !expected.contains(&column.r#type) {
+                findings.push(finding(DefinitionProblem::WrongType {
+                    expected,
+                    actual: column.r#type.clone(),
+                }));
+            }
+            if column.last_written.is_none_or(|t| t < oldest) {
+                findings.push(finding(DefinitionProblem::Stale {
+                    last_written: column.last_written,
+                }));
+            }
+        }
+        findings
+    }
+}
+
+impl HoneyComb {
+    pub async fn get_dataset_definitions(
+        &self,
+        dataset_slug: &str,
+    ) -> anyhow::Result<DatasetDefinitions> {
+        self.get(&format!("dataset_definitions/{}", dataset_slug))
+            .await
+    }
+
+    /// Change the definitions that are set, returning all of them.
+    pub async fn update_dataset_definitions(
+        &self,
+        dataset_slug: &str,
+        changes: &DatasetDefinitions,
+    ) -> anyhow::Result<DatasetDefinitions> {
+        self.patch(
+            &format!("dataset_definitions/{}", dataset_slug),
+            serde_json::to_value(changes)?,
+        )
+        .await
+    }
+
+    /// Fetch a dataset's definitions and columns and check them, see
+    /// [`DatasetDefinitions::validate`].
+    pub async fn check_dataset_definitions(
+        &self,
+        dataset_slug: &str,
+        max_age: Duration,
+    ) -> anyhow::Result<Vec<DefinitionFinding>> {
+        let definitions = self.get_dataset_definitions(dataset_slug).await?;
+        let columns = self.list_all_columns(dataset_slug).await?;
+        Ok(definitions.validate(&columns, self.now(), max_age))
+    }
+}
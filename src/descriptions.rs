@@ -0,0 +1,243 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::Context;
+use futures::{stream, StreamExt};
+
+use crate::{
+    column::ColumnUpdate,
+    honeycomb::{Column, HoneyComb},
+};
+
+/// A column description to apply, from one row of a `dataset,key_name,description` CSV.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptionRow {
+    /// The 1-based line the row starts on.
+    pub line: usize,
+    pub dataset: String,
+    pub key_name: String,
+    pub description: String,
+}
+
+/// The outcome of applying description rows.
+#[derive(Debug, Default)]
+pub struct DescriptionImport {
+    /// Rows whose column was updated.
+    pub changed: Vec<DescriptionRow>,
+    /// Rows whose column already had the description.
+    pub unchanged: Vec<DescriptionRow>,
+    /// Rows not applied, with the reason, e.g. no such column.
+    pub skipped: Vec<(DescriptionRow, String)>,
+    pub failed: Vec<(DescriptionRow, anyhow::Error)>,
+}
+
+impl DescriptionImport {
+    /// Rows that named an existing column, changed or not.
+    pub fn matched(&self) -> impl Iterator<Item = &DescriptionRow> {
+        self.changed.iter().chain(&self.unchanged)
+    }
+}
+
+/// Split CSV text into records of fields, with each record's starting line. Fields may
+/// be quoted, with `""` for a quote and newlines allowed inside quotes.
+fn csv_records(text: &str) -> anyhow::Result<Vec<(usize, Vec<String>)>> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => fields.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                fields.push(std::mem::take(&mut field));
+                records.push((start, std::mem::take(&mut fields)));
+                line += 1;
+                start = line;
+            }
+            (_, c) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    anyhow::ensure!(
+        !quoted,
+        "unterminated quote in the row starting on line {}",
+        start
+    );
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((start, fields));
+    }
+    Ok(records)
+}
+
+impl DescriptionRow {
+    /// Parse `dataset,key_name,description` rows, skipping blank lines and an optional
+    /// header row. The header is matched ignoring case and spaces, and a leading byte
+    /// order mark, as spreadsheets add, is ignored.
+    pub fn parse_csv(text: &str) -> anyhow::Result<Vec<Self>> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let mut rows = Vec::new();
+        let mut first = true;
+        for (line, fields) in csv_records(text)? {
+            if fields.iter().all(|f| f.trim().is_empty()) {
+                continue;
+            }
+            let [dataset, key_name, description] = &fields[..] else {
+                anyhow::bail!(
+                    "line {} has {} fields, expected dataset,key_name,description",
+                    line,
+                    fields.len()
+                );
+            };
+            let header = first
+                && dataset.trim().eq_ignore_ascii_case("dataset")
+                && key_name.trim().eq_ignore_ascii_case("key_name");
+            first = false;
+            if header {
+                continue;
+            }
+            rows.push(Self {
+                line,
+                dataset: dataset.trim().to_string(),
+                key_name: key_name.trim().to_string(),
+                description: description.clone(),
+            });
+        }
+        Ok(rows)
+    }
+
+    pub fn load_csv(path: impl AsRef<Path>) -> anyhow::Result<Vec<Self>> {
+        let path = path.as_ref();
+        let text =
+            fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+        Self::parse_csv(&text).context(format!("Invalid CSV {}", path.display()))
+    }
+}
+
+impl HoneyComb {
+    /// Set column descriptions from rows, e.g. a spreadsheet exported with
+    /// [`DescriptionRow::load_csv`]. Columns that already have the description are left
+    /// alone; rows for unknown datasets or columns are skipped.
+    pub async fn import_column_descriptions(&self, rows: &[DescriptionRow]) -> DescriptionImport {
+        let mut by_dataset = BTreeMap::<&str, Vec<&DescriptionRow>>::new();
+        for row in rows {
+            by_dataset.entry(&row.dataset).or_default().push(row);
+        }
+
+        let mut import = DescriptionImport::default();
+        let mut updates = Vec::new();
+        let mut listings =
+            stream::iter(by_dataset)
+                .map(|(dataset_slug, rows)| async move {
+                    (rows, self.list_all_columns(dataset_slug).await)
+                })
+                .buffered(5);
+        while let Some((rows, columns)) = listings.next().await {
+            let columns: Vec<Column> = match columns {
+                Ok(columns) => columns,
+                Err(e) => {
+                    let reason = format!("couldn't list columns: {:#}", e);
+                    import
+                        .skipped
+                        .extend(rows.into_iter().map(|r| (r.clone(), reason.clone())));
+                    continue;
+                }
+            };
+            for row in rows {
+                match columns.iter().find(|c| c.key_name == row.key_name) {
+                    None => import
+                        .skipped
+                        .push((row.clone(), "no such column".to_string())),
+                    Some(column) if column.description == row.description => {
+                        import.unchanged.push(row.clone())
+                    }
+                    Some(column) => updates.push((row, column.id.clone())),
+                }
+            }
+        }
+
+        let mut tasks = stream::iter(updates)
+            .map(|(row, column_id)| async move {
+                let changes = ColumnUpdate {
+                    description: Some(row.description.clone()),
+                    ..Default::default()
+                };
+                let result = self.update_column(&row.dataset, &column_id, &changes).await;
+                (row, result)
+            })
+            .buffer_unordered(5);
+        while let Some((row, result)) = tasks.next().await {
+            match result {
+                Ok(_) => import.changed.push(row.clone()),
+                Err(e) => import.failed.push((row.clone(), e)),
+            }
+        }
+        import
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(text: &str) -> Vec<Vec<String>> {
+        csv_records(text)
+            .unwrap()
+            .into_iter()
+            .map(|(_, fields)| fields)
+            .collect()
+    }
+
+    #[test]
+    fn quoted_fields_may_hold_commas_quotes_and_newlines() {
+        assert_eq!(
+            fields("a,\"b, c\",\"say \"\"hi\"\"\"\nd,\"two\nlines\",e"),
+            vec![
+                vec!["a", "b, c", "say \"hi\""],
+                vec!["d", "two\nlines", "e"]
+            ]
+        );
+        let lines = csv_records("a,\"b\nc\",d\ne,f,g").unwrap();
+        assert_eq!(lines[1].0, 3);
+        assert!(csv_records("a,\"b,c\n").is_err());
+    }
+
+    #[test]
+    fn crlf_ends_a_record() {
+        assert_eq!(
+            fields("a,\"b\",c\r\nd,e,f\r\n"),
+            vec![vec!["a", "b", "c"], vec!["d", "e", "f"]]
+        );
+    }
+
+    #[test]
+    fn parse_csv_skips_a_header_after_a_bom_and_blank_lines() {
+        let rows = DescriptionRow::parse_csv(
+            "\u{feff}\r\n Dataset , KEY_NAME ,description\r\ncheckout, user.id ,\"The user, if known\"\r\n",
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![DescriptionRow {
+                line: 3,
+                dataset: "checkout".to_string(),
+                key_name: "user.id".to_string(),
+                description: "The user, if known".to_string(),
+            }]
+        );
+        assert!(DescriptionRow::parse_csv("checkout,user.id\n").is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::query::QueryResultData;
+
+/// How one calculation changed for a group present in both results.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct MetricDelta {
+    /// The result column, e.g. `P99(duration_ms)`.
+    pub metric: String,
+    pub before: Option<f64>,
+    pub after: Option<f64>,
+    /// `after - before`, when both are present.
+    pub change: Option<f64>,
+    /// The change as a percentage of `before`, unless `before` is missing or zero.
+    pub percent: Option<f64>,
+}
+
+/// A breakdown group present in both results with different calculations.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct GroupDiff {
+    /// The breakdown values identifying the group.
+    pub group: Map<String, Value>,
+    pub deltas: Vec<MetricDelta>,
+}
+
+/// The row by row difference between two results of the same query, e.g. this week
+/// against last week.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct ResultDiff {
+    /// Rows for groups only in the newer result.
+    pub added: Vec<Map<String, Value>>,
+    /// Rows for groups only in the older result.
+    pub removed: Vec<Map<String, Value>>,
+    pub changed: Vec<GroupDiff>,
+    /// The number of groups whose calculations are all equal.
+    pub unchanged: usize,
+}
+
+impl ResultDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Split a row into its group, the breakdown values, and its numeric calculations.
+/// Without breakdowns, every non-numeric field is part of the group.
+fn split_row(
+    row: &Map<String, Value>,
+    breakdowns: &[String],
+) -> (Map<String, Value>, Map<String, Value>) {
+    row.iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .partition(|(k, v)| {
+            if breakdowns.is_empty() {
+                !v.is_number()
+            } else {
+                breakdowns.contains(k)
+            }
+        })
+}
+
+impl QueryResultData {
+    /// Compare with `newer`, matching rows on the query's breakdowns. Groups are listed in
+    /// the order they appear, so ordered queries stay ordered.
+    pub fn diff(&self, newer: &Self) -> ResultDiff {
+        let breakdowns = self
+            .query
+            .as_ref()
+            .or(newer.query.as_ref())
+            .map(|q| q.breakdowns.iter().map(|b| b.0.clone()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let before = self
+            .results
+            .iter()
+            .map(|row| split_row(row, &breakdowns))
+            .collect::<Vec<_>>();
+        let after = newer
+            .results
+            .iter()
+            .map(|row| split_row(row, &breakdowns))
+            .collect::<Vec<_>>();
+
+        let mut diff = ResultDiff::default();
+        for ((group, old), row) in before.iter().zip(&self.results) {
+            let Some((_, new)) = after.iter().find(|(g, _)| g == group) else {
+                diff.removed.push(row.clone());
+                continue;
+            };
+            let mut metrics = old.keys().collect::<Vec<_>>();
+            metrics.extend(new.keys().filter(|k| !old.contains_key(*k)));
+            let deltas = metrics
+                .into_iter()
+                .filter(|m| old.get(*m) != new.get(*m))
+                .map(|metric| {
+                    let before = old.get(metric).and_then(Value::as_f64);
+                    let after = new.get(metric).and_then(Value::as_f64);
+                    let change = before.zip(after).map(|(b, a)| a - b);
+                    MetricDelta {
+                        metric: metric.clone(),
+                        before,
+                        after,
+                        change,
+                        percent: change
+                            .zip(before.filter(|b| *b != 0.0))
+                            .map(|(c, b)| c / b.abs() * 100.0),
+                    }
+                })
+                .collect::<Vec<_>>();
+            if deltas.is_empty() {
+                diff.unchanged += 1;
+            } else {
+                diff.changed.push(GroupDiff {
+                    group: group.clone(),
+                    deltas,
+                });
+            }
+        }
+        diff.added = after
+            .iter()
+            .zip(&newer.results)
+            .filter(|((group, _), _)| !before.iter().any(|(g, _)| g == group))
+            .map(|(_, row)| row.clone())
+            .collect();
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn result(breakdowns: &[&str], rows: Value) -> QueryResultData {
+        let rows = rows
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| json!({ "data": row }))
+            .collect::<Vec<_>>();
+        serde_json::from_value(json!({
+            "complete": true,
+            "query": {"breakdowns": breakdowns, "calculations": [{"op": "COUNT"}]},
+            "data": {"results": rows}
+        }))
+        .unwrap()
+    }
+
+    fn row(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn groups_are_matched_on_breakdowns() {
+        let before = result(
+            &["service"],
+            json!([
+                {"service": "cart", "COUNT": 10},
+                {"service": "checkout", "COUNT": 5},
+                {"service": "search", "COUNT": 1}
+            ]),
+        );
+        let after = result(
+            &["service"],
+            json!([
+                {"service": "checkout", "COUNT": 5},
+                {"service": "cart", "COUNT": 15},
+                {"service": "login", "COUNT": 2}
+            ]),
+        );
+        let diff = before.diff(&after);
+        assert_eq!(
+            diff.added,
+            vec![row(json!({"service": "login", "COUNT": 2}))]
+        );
+        assert_eq!(
+            diff.removed,
+            vec![row(json!({"service": "search", "COUNT": 1}))]
+        );
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(
+            diff.changed,
+            vec![GroupDiff {
+                group: row(json!({"service": "cart"})),
+                deltas: vec![MetricDelta {
+                    metric: "COUNT".to_string(),
+                    before: Some(10.0),
+                    after: Some(15.0),
+                    change: Some(5.0),
+                    percent: Some(50.0),
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn a_metric_missing_on_one_side_has_no_change() {
+        let before = result(&[], json!([{"COUNT": 0}]));
+        let after = result(&[], json!([{"COUNT": 3, "MAX(duration_ms)": 12.5}]));
+        let deltas = &before.diff(&after).changed[0].deltas;
+        assert_eq!(deltas[0].change, Some(3.0));
+        assert_eq!(deltas[0].percent, None);
+        assert_eq!(deltas[1].before, None);
+        assert_eq!(deltas[1].change, None);
+    }
+
+    #[test]
+    fn identical_results_are_empty() {
+        let before = result(&["service"], json!([{"service": "cart", "COUNT": 1}]));
+        let diff = before.diff(&before.clone());
+        assert!(diff.is_empty());
+        assert_eq!(diff.unchanged, 1);
+    }
+}
@@ -0,0 +1,176 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{board::Board, honeycomb::HoneyComb};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BundleTrigger {
+    pub dataset: String,
+    /// The trigger definition as accepted by the Triggers API, identified by `name`.
+    pub trigger: Value,
+}
+
+/// The desired state of triggers and boards, as kept in git.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct GitOpsBundle {
+    #[serde(default)]
+    pub triggers: Vec<BundleTrigger>,
+    /// Boards are identified by `name`.
+    #[serde(default)]
+    pub boards: Vec<Board>,
+}
+
+impl GitOpsBundle {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text =
+            fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&text).context(format!("Invalid bundle {}", path.display()))
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceKind {
+    Trigger,
+    Board,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FieldDrift {
+    /// JSON pointer to the field, e.g. `/threshold/value`.
+    pub path: String,
+    pub expected: Value,
+    pub live: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DriftStatus {
+    /// In the bundle but not found live.
+    Missing,
+    /// Found live with fields that differ from the bundle.
+    Changed { fields: Vec<FieldDrift> },
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct DriftItem {
+    pub kind: ResourceKind,
+    /// The dataset for triggers, `None` for boards.
+    pub dataset: Option<String>,
+    pub name: String,
+    #[serde(flatten)]
+    pub status: DriftStatus,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct DriftReport {
+    pub items: Vec<DriftItem>,
+}
+
+impl DriftReport {
+    pub fn has_drift(&self) -> bool {
+        !self.items.is_empty()
+    }
+}
+
+/// Compare only the fields present in `expected`, so values the server fills in
+/// (ids, timestamps, defaults) don't count as drift.
+fn diff_fields(path: &str, expected: &Value, live: Option<&Value>, drift: &mut Vec<FieldDrift>) {
+    match (expected, live) {
+        (Value::Object(expected), Some(Value::Object(live))) => {
+            for (key, value) in expected {
+                diff_fields(
+                    &format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1")),
+                    value,
+                    live.get(key),
+                    drift,
+                );
+            }
+        }
+        (expected, live) if live != Some(expected) => drift.push(FieldDrift {
+            path: path.to_string(),
+            expected: expected.clone(),
+            live: live.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+fn compare(
+    kind: ResourceKind,
+    dataset: Option<&str>,
+    expected: &Value,
+    live: &[Value],
+) -> Option<DriftItem> {
+    let name = expected["name"].as_str().unwrap_or_default();
+    let status = match live.iter().find(|l| l["name"].as_str() == Some(name)) {
+        None => DriftStatus::Missing,
+        Some(live) => {
+            let mut fields = Vec::new();
+            diff_fields("", expected, Some(live), &mut fields);
+            if fields.is_empty() {
+                return None;
+            }
+            DriftStatus::Changed { fields }
+        }
+    };
+    Some(DriftItem {
+        kind,
+        dataset: dataset.map(str::to_string),
+        name: name.to_string(),
+        status,
+    })
+}
+
+impl HoneyComb {
+    pub async fn list_triggers(&self, dataset_slug: &str) -> anyhow::Result<Vec<Value>> {
+        self.get(&format!("triggers/{}", dataset_slug)).await
+    }
+
+    /// Compare a bundle against the live triggers and boards without changing anything,
+    /// so a scheduled job can alert on edits made outside of git.
+    pub async fn detect_drift(&self, bundle: &GitOpsBundle) -> anyhow::Result<DriftReport> {
+        let mut report = DriftReport::default();
+
+        let mut datasets = bundle
+            .triggers
+            .iter()
+            .map(|t| t.dataset.as_str())
+            .collect::<Vec<_>>();
+        datasets.sort();
+        datasets.dedup();
+        for dataset in datasets {
+            let live = self.list_triggers(dataset).await?;
+            report.items.extend(
+                bundle
+                    .triggers
+                    .iter()
+                    .filter(|t| t.dataset == dataset)
+                    .filter_map(|t| {
+                        compare(ResourceKind::Trigger, Some(dataset), &t.trigger, &live)
+                    }),
+            );
+        }
+
+        if !bundle.boards.is_empty() {
+            let live = self
+                .list_boards()
+                .await?
+                .iter()
+                .map(serde_json::to_value)
+                .collect::<Result<Vec<_>, _>>()?;
+            for board in &bundle.boards {
+                let expected = serde_json::to_value(board)?;
+                report
+                    .items
+                    .extend(compare(ResourceKind::Board, None, &expected, &live));
+            }
+        }
+
+        Ok(report)
+    }
+}
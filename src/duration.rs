@@ -0,0 +1,121 @@
+use std::fmt::{Display, Formatter};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::honeycomb::{Column, ColumnType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DurationUnit {
+    Nanoseconds,
+    Microseconds,
+    Milliseconds,
+    Seconds,
+    Minutes,
+}
+
+/// Key name suffixes for each unit, matched after a `_` or `.` separator.
+const SUFFIXES: &[(DurationUnit, &[&str])] = &[
+    (DurationUnit::Nanoseconds, &["ns", "nanos", "nanoseconds"]),
+    (
+        DurationUnit::Microseconds,
+        &["us", "micros", "microseconds"],
+    ),
+    (
+        DurationUnit::Milliseconds,
+        &["ms", "millis", "milliseconds"],
+    ),
+    (DurationUnit::Seconds, &["s", "sec", "secs", "seconds"]),
+    (DurationUnit::Minutes, &["min", "mins", "minutes"]),
+];
+
+/// Calculations whose result is not in the unit of the column.
+const UNITLESS_OPS: &[&str] = &["COUNT", "COUNT_DISTINCT", "CONCURRENCY", "HEATMAP"];
+
+impl DurationUnit {
+    /// Detect the unit from a key name such as `duration_ms` or `db.query.seconds`.
+    pub fn from_key_name(key_name: &str) -> Option<Self> {
+        let key_name = key_name.to_lowercase();
+        let (_, suffix) = key_name.rsplit_once(['_', '.'])?;
+        SUFFIXES
+            .iter()
+            .find(|(_, suffixes)| suffixes.contains(&suffix))
+            .map(|(unit, _)| *unit)
+    }
+
+    fn seconds(self) -> f64 {
+        match self {
+            Self::Nanoseconds => 1e-9,
+            Self::Microseconds => 1e-6,
+            Self::Milliseconds => 1e-3,
+            Self::Seconds => 1.0,
+            Self::Minutes => 60.0,
+        }
+    }
+
+    pub fn convert(self, value: f64, to: DurationUnit) -> f64 {
+        value * self.seconds() / to.seconds()
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Nanoseconds => "ns",
+            Self::Microseconds => "us",
+            Self::Milliseconds => "ms",
+            Self::Seconds => "s",
+            Self::Minutes => "min",
+        }
+    }
+}
+
+/// The numeric columns that look like durations, with their detected unit.
+pub fn duration_columns(columns: &[Column]) -> Vec<(&Column, DurationUnit)> {
+    columns
+        .iter()
+        .filter(|c| matches!(c.r#type, ColumnType::Float | ColumnType::Integer))
+        .filter_map(|c| Some((c, DurationUnit::from_key_name(&c.key_name)?)))
+        .collect()
+}
+
+/// A duration from a result row, converted to a common unit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DurationValue {
+    /// The key in the result row, e.g. `P99(duration_ms)`.
+    pub key: String,
+    pub value: f64,
+    pub unit: DurationUnit,
+    /// The unit the value was recorded in.
+    pub source_unit: DurationUnit,
+}
+
+impl Display for DurationValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} {}", self.key, self.value, self.unit.label())
+    }
+}
+
+/// The column a result key refers to, or `None` if the value isn't in the column's unit.
+/// Keys are either a column name (raw rows, breakdowns) or `OP(column)`.
+fn key_column(key: &str) -> Option<&str> {
+    match key.strip_suffix(')').and_then(|k| k.split_once('(')) {
+        Some((op, _)) if UNITLESS_OPS.contains(&op) || op.starts_with("RATE_") => None,
+        Some((_, column)) => Some(column),
+        None => Some(key),
+    }
+}
+
+/// Convert every duration in a query result row (the `data` object of a result) to `to`.
+pub fn normalize_row(data: &Map<String, Value>, to: DurationUnit) -> Vec<DurationValue> {
+    data.iter()
+        .filter_map(|(key, value)| {
+            let source_unit = DurationUnit::from_key_name(key_column(key)?)?;
+            Some(DurationValue {
+                key: key.clone(),
+                value: source_unit.convert(value.as_f64()?, to),
+                unit: to,
+                source_unit,
+            })
+        })
+        .collect()
+}
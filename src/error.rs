@@ -0,0 +1,538 @@
+use std::{
+    fmt::{Display, Formatter},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{capabilities::Capability, guardrails::Violation, honeycomb::Authorizations};
+
+/// What kind of key an API key looks like, judged by its format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyKind {
+    Ingest,
+    Configuration,
+    Management,
+    Unknown,
+}
+
+impl KeyKind {
+    pub fn of(api_key: &str) -> Self {
+        if api_key.starts_with("hcaik_") || api_key.starts_with("hcxik_") {
+            Self::Ingest
+        } else if api_key.starts_with("hcamk_") || api_key.starts_with("hcxmk_") {
+            Self::Management
+        } else if api_key.len() == 22 && api_key.chars().all(|c| c.is_ascii_alphanumeric()) {
+            Self::Configuration
+        } else {
+            Self::Unknown
+        }
+    }
+
+    fn hint(&self) -> &'static str {
+        match self {
+            Self::Ingest => "the key looks like an ingest key, which can only send events; use a configuration key",
+            Self::Management => "the key looks like a management key; use an environment configuration key",
+            Self::Configuration => "the key looks like a configuration key, check it hasn't been revoked and has the required access",
+            Self::Unknown => "the key is not in a recognised format, check it is a configuration key",
+        }
+    }
+}
+
+/// Errors with a meaning beyond "the request failed". These are returned inside
+/// `anyhow::Error` so callers can `downcast_ref::<ApiError>()` to handle them.
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    /// 401: the key was not accepted at all.
+    Unauthorized { key_kind: KeyKind },
+    /// 403: the key is valid but lacks access for this request.
+    Forbidden { request: String, key_kind: KeyKind },
+    /// The account or key doesn't support a feature.
+    Unsupported {
+        capability: Capability,
+        reason: &'static str,
+    },
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unauthorized { key_kind } => {
+                write!(f, "unauthorized: {}", key_kind.hint())
+            }
+            Self::Forbidden { request, key_kind } => {
+                write!(f, "forbidden: {}: {}", request, key_kind.hint())
+            }
+            Self::Unsupported { capability, reason } => {
+                write!(f, "unsupported: {:?}: {}", capability, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// The part of a query spec that a validation error refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryPart {
+    Calculations,
+    Filters,
+    FilterCombination,
+    Breakdowns,
+    Orders,
+    Havings,
+    Limit,
+    TimeRange,
+    Granularity,
+    Other,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldError {
+    /// Path into the spec, e.g. `calculations[0].op`.
+    pub field: String,
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl FieldError {
+    pub fn part(&self) -> QueryPart {
+        let root = self.field.split(['.', '[']).next().unwrap_or_default();
+        match root {
+            "calculations" => QueryPart::Calculations,
+            "filters" => QueryPart::Filters,
+            "filter_combination" => QueryPart::FilterCombination,
+            "breakdowns" => QueryPart::Breakdowns,
+            "orders" => QueryPart::Orders,
+            "havings" => QueryPart::Havings,
+            "limit" => QueryPart::Limit,
+            "time_range" | "start_time" | "end_time" => QueryPart::TimeRange,
+            "granularity" => QueryPart::Granularity,
+            _ => QueryPart::Other,
+        }
+    }
+}
+
+/// A query spec rejected by the API with `422 Unprocessable Entity`.
+#[derive(Debug, Clone)]
+pub struct QueryValidationError {
+    pub request: String,
+    pub message: String,
+    pub fields: Vec<FieldError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ValidationBody {
+    error: Option<String>,
+    title: Option<String>,
+    #[serde(default)]
+    type_detail: Vec<FieldError>,
+}
+
+impl QueryValidationError {
+    pub(crate) fn from_body(request: &str, body: &str) -> Self {
+        match serde_json::from_str::<ValidationBody>(body) {
+            Ok(b) => Self {
+                request: request.to_string(),
+                message: b.error.or(b.title).unwrap_or_else(|| body.to_string()),
+                fields: b.type_detail,
+            },
+            Err(_) => Self {
+                request: request.to_string(),
+                message: body.to_string(),
+                fields: vec![],
+            },
+        }
+    }
+
+    /// The parts of the spec that were rejected.
+    pub fn parts(&self) -> Vec<QueryPart> {
+        let mut parts = Vec::new();
+        for part in self.fields.iter().map(FieldError::part) {
+            if !parts.contains(&part) {
+                parts.push(part);
+            }
+        }
+        parts
+    }
+}
+
+impl Display for QueryValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid query: {}", self.message)?;
+        for field in &self.fields {
+            write!(f, "\n  {}: {}", field.field, field.description)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for QueryValidationError {}
+
+/// A dataset that couldn't be deleted because delete protection is on.
+#[derive(Debug, Clone)]
+pub struct DeleteProtected {
+    pub dataset: String,
+}
+
+impl Display for DeleteProtected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "dataset {} is delete protected, turn protection off before deleting it",
+            self.dataset
+        )
+    }
+}
+
+impl std::error::Error for DeleteProtected {}
+
+/// An error status with no more specific error type, e.g. `404 Not Found` for a
+/// misspelled dataset slug or an ID that was already deleted.
+#[derive(Debug, Clone)]
+pub struct StatusError {
+    pub request: String,
+    pub status: u16,
+    /// The start of the response body.
+    pub body: String,
+}
+
+impl StatusError {
+    pub(crate) fn new(request: String, status: u16, body: &str) -> Self {
+        Self {
+            request,
+            status,
+            body: snippet(body),
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status == 404
+    }
+}
+
+impl Display for StatusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} failed with status {}: {}",
+            self.request, self.status, self.body
+        )
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+/// A query refused by the client's guardrails before it was created.
+#[derive(Debug, Clone)]
+pub struct GuardrailRefused {
+    pub dataset: String,
+    pub violations: Vec<Violation>,
+}
+
+impl Display for GuardrailRefused {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "query on {} refused by guardrails", self.dataset)?;
+        for violation in &self.violations {
+            write!(f, "\n  {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for GuardrailRefused {}
+
+/// A mistake in a query spec found before sending it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryProblem {
+    pub part: QueryPart,
+    pub message: String,
+}
+
+/// A query spec that the API would reject, caught without using an API call.
+#[derive(Debug, Clone)]
+pub struct InvalidQuery {
+    pub problems: Vec<QueryProblem>,
+}
+
+impl Display for InvalidQuery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid query")?;
+        for problem in &self.problems {
+            write!(f, "\n  {:?}: {}", problem.part, problem.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidQuery {}
+
+/// A column referenced by a query, and what is wrong with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaProblem {
+    pub part: QueryPart,
+    pub column: String,
+    pub reason: String,
+}
+
+/// A query that doesn't fit the dataset's schema, caught before it was sent.
+#[derive(Debug, Clone)]
+pub struct SchemaMismatch {
+    pub dataset: String,
+    pub problems: Vec<SchemaProblem>,
+}
+
+impl Display for SchemaMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "query does not match the schema of {}", self.dataset)?;
+        for problem in &self.problems {
+            write!(
+                f,
+                "\n  {:?} {}: {}",
+                problem.part, problem.column, problem.reason
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SchemaMismatch {}
+
+/// The key lacks access types the caller requires.
+#[derive(Debug)]
+pub struct MissingAccess {
+    pub missing: Vec<String>,
+    pub authorizations: Authorizations,
+}
+
+impl Display for MissingAccess {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "honeycomb: missing required access {:?}:\n{}",
+            self.missing, self.authorizations
+        )
+    }
+}
+
+impl std::error::Error for MissingAccess {}
+
+/// The operation was aborted by the client's cancellation token.
+#[derive(Debug, Clone, Copy)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Every attempt allowed by the retry policy was rate limited or failed transiently.
+#[derive(Debug, Clone)]
+pub struct RetriesExhausted {
+    pub request: String,
+    pub attempts: u32,
+    pub last_error: String,
+    /// The last attempt was refused with `429 Too Many Requests`.
+    pub rate_limited: bool,
+}
+
+impl Display for RetriesExhausted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "too many retries: {} failed {} times, last error: {}",
+            self.request, self.attempts, self.last_error
+        )
+    }
+}
+
+impl std::error::Error for RetriesExhausted {}
+
+/// A query result that was still running when the poll deadline passed.
+#[derive(Debug, Clone)]
+pub struct QueryTimeout {
+    pub dataset: String,
+    pub query_result_id: String,
+    pub waited: Duration,
+}
+
+impl Display for QueryTimeout {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "query result {} in {} did not complete within {:?}",
+            self.query_result_id, self.dataset, self.waited
+        )
+    }
+}
+
+impl std::error::Error for QueryTimeout {}
+
+const SNIPPET_LEN: usize = 500;
+
+/// The start of a response body, to keep errors readable.
+fn snippet(body: &str) -> String {
+    match body.char_indices().nth(SNIPPET_LEN) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body.to_string(),
+    }
+}
+
+/// A response body that didn't match the expected type.
+#[derive(Debug, Clone)]
+pub struct DeserializeError {
+    pub request: String,
+    pub status: u16,
+    /// Path to the failing field, e.g. `[3].last_written`, or `.` for the root.
+    pub path: String,
+    pub message: String,
+    /// The start of the response body.
+    pub snippet: String,
+}
+
+impl DeserializeError {
+    pub(crate) fn new(
+        request: String,
+        status: u16,
+        path: String,
+        message: String,
+        body: &str,
+    ) -> Self {
+        Self {
+            request,
+            status,
+            path,
+            message,
+            snippet: snippet(body),
+        }
+    }
+}
+
+impl Display for DeserializeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to parse JSON data: {} (status {}) at {}: {}\nbody: {}",
+            self.request, self.status, self.path, self.message, self.snippet
+        )
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+/// In strict parsing mode, a response had fields the types don't model.
+#[derive(Debug, Clone)]
+pub struct UnknownFields {
+    pub request: String,
+    /// Paths to the unknown fields, e.g. `?.created_at` for a field of every list item.
+    pub fields: Vec<String>,
+}
+
+impl Display for UnknownFields {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown fields in response to {}: {}",
+            self.request,
+            self.fields.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownFields {}
+
+/// A coarse classification of errors for tools to branch on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    AuthFailure,
+    MissingAccess,
+    RateLimited,
+    ValidationFailed,
+    Unsupported,
+    Cancelled,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn of(e: &anyhow::Error) -> Self {
+        if let Some(e) = e.downcast_ref::<ApiError>() {
+            match e {
+                ApiError::Unauthorized { .. } => Self::AuthFailure,
+                ApiError::Forbidden { .. } => Self::MissingAccess,
+                ApiError::Unsupported { .. } => Self::Unsupported,
+            }
+        } else if e.is::<MissingAccess>() {
+            Self::MissingAccess
+        } else if e.is::<QueryValidationError>()
+            || e.is::<SchemaMismatch>()
+            || e.is::<InvalidQuery>()
+            || e.is::<GuardrailRefused>()
+        {
+            Self::ValidationFailed
+        } else if e.is::<Cancelled>() {
+            Self::Cancelled
+        } else if e
+            .downcast_ref::<RetriesExhausted>()
+            .is_some_and(|e| e.rate_limited)
+        {
+            Self::RateLimited
+        } else {
+            Self::Other
+        }
+    }
+
+    /// The process exit code for a CLI. 2 is left for argument errors and 130 matches
+    /// the shell convention for Ctrl-C.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Other => 1,
+            Self::AuthFailure => 3,
+            Self::MissingAccess => 4,
+            Self::RateLimited => 5,
+            Self::ValidationFailed => 6,
+            Self::Unsupported => 7,
+            Self::Cancelled => 130,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ErrorDetail {
+    pub kind: ErrorKind,
+    pub exit_code: i32,
+    pub message: String,
+    /// The chain of underlying causes, outermost first.
+    pub causes: Vec<String>,
+}
+
+/// A JSON error document for `--output json` style output:
+/// `{"error": {"kind": "rate_limited", "exit_code": 5, ...}}`.
+#[derive(Debug, Serialize, Clone)]
+pub struct ErrorEnvelope {
+    pub error: ErrorDetail,
+}
+
+impl ErrorEnvelope {
+    pub fn new(e: &anyhow::Error) -> Self {
+        let kind = ErrorKind::of(e);
+        Self {
+            error: ErrorDetail {
+                kind,
+                exit_code: kind.exit_code(),
+                message: e.to_string(),
+                causes: e.chain().skip(1).map(|c| c.to_string()).collect(),
+            },
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.error.exit_code
+    }
+}
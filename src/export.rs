@@ -0,0 +1,139 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::query::QueryResultData;
+
+/// How query result rows and events are shaped for export.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Turn dotted keys like `http.request.method` into nested objects.
+    pub unflatten: bool,
+    pub separator: char,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            unflatten: false,
+            separator: '.',
+        }
+    }
+}
+
+/// Nest dotted keys: `{"http.request.method": "GET"}` becomes
+/// `{"http": {"request": {"method": "GET"}}}`. When a key is both a value and a prefix,
+/// e.g. `http` and `http.method`, the value wins its place and the longer key is kept
+/// flat in the nearest object so nothing is lost.
+pub fn unflatten(row: &Map<String, Value>, separator: char) -> Map<String, Value> {
+    let mut keys = row.keys().collect::<Vec<_>>();
+    // Shorter keys first so plain values claim their place before nested ones
+    keys.sort_by_key(|k| k.matches(separator).count());
+
+    let mut nested = Map::new();
+    for key in keys {
+        let value = row[key].clone();
+        let mut parts = key.split(separator).peekable();
+        let mut object = &mut nested;
+        let mut rest = key.as_str();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                object.insert(part.to_string(), value);
+                break;
+            }
+            let is_object = object
+                .entry(part.to_string())
+                .or_insert_with(|| Value::Object(Map::new()))
+                .is_object();
+            if !is_object {
+                object.insert(rest.to_string(), value);
+                break;
+            }
+            rest = &rest[part.len() + separator.len_utf8()..];
+            object = object
+                .get_mut(part)
+                .and_then(Value::as_object_mut)
+                .expect("checked above");
+        }
+    }
+    nested
+}
+
+fn shape(row: &Map<String, Value>, options: &ExportOptions) -> Value {
+    if options.unflatten {
+        Value::Object(unflatten(row, options.separator))
+    } else {
+        Value::Object(row.clone())
+    }
+}
+
+/// Shape rows for export, each already a map of column to value.
+pub fn export_rows(rows: &[Map<String, Value>], options: &ExportOptions) -> Vec<Value> {
+    rows.iter().map(|row| shape(row, options)).collect()
+}
+
+/// Shape the rows of a query result for export.
+pub fn export_results(output: &QueryResultData, options: &ExportOptions) -> Vec<Value> {
+    export_rows(&output.results, options)
+}
+
+/// Shape events for export, e.g. sampled raw events or trace spans. Each event must
+/// serialize to a JSON object.
+pub fn export_events<T: Serialize>(
+    events: &[T],
+    options: &ExportOptions,
+) -> anyhow::Result<Vec<Value>> {
+    events
+        .iter()
+        .enumerate()
+        .map(|(i, event)| match serde_json::to_value(event)? {
+            Value::Object(event) => Ok(shape(&event, options)),
+            other => anyhow::bail!("event {} is not an object: {}", i, other),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn unflattened() -> ExportOptions {
+        ExportOptions {
+            unflatten: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_column_named_data_is_kept() {
+        let output: QueryResultData = serde_json::from_value(json!({
+            "complete": true,
+            "data": {"results": [{"data": {"data": {"size": 1}, "http.method": "GET"}}]}
+        }))
+        .unwrap();
+        assert_eq!(
+            export_results(&output, &unflattened()),
+            vec![json!({"data": {"size": 1}, "http": {"method": "GET"}})]
+        );
+    }
+
+    #[test]
+    fn events_are_exported_whole() {
+        let events = [json!({"data": "x", "a.b": 1}), json!(1)];
+        assert_eq!(
+            export_events(&events[..1], &unflattened()).unwrap(),
+            vec![json!({"data": "x", "a": {"b": 1}})]
+        );
+        assert!(export_events(&events, &unflattened()).is_err());
+    }
+
+    #[test]
+    fn a_value_key_keeps_its_place_over_a_longer_key() {
+        let row = json!({"http": "yes", "http.method": "GET"});
+        assert_eq!(
+            Value::Object(unflatten(row.as_object().unwrap(), '.')),
+            json!({"http": "yes", "http.method": "GET"})
+        );
+    }
+}
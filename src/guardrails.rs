@@ -0,0 +1,206 @@
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{error::GuardrailRefused, query::QuerySpec};
+
+const DAY: u64 = 86400;
+
+/// What to do with a query that trips a guardrail.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GuardrailAction {
+    /// Fail with [`GuardrailRefused`] without creating the query.
+    #[default]
+    Refuse,
+    /// Run the query, recording the violation for [`Guardrails::warnings`].
+    Warn,
+}
+
+/// An expensive query shape.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// A query without calculations, returning raw events, over a range longer than allowed.
+    RawEventRange { seconds: usize, max_seconds: usize },
+    /// A breakdown on a column listed as high cardinality.
+    HighCardinalityBreakdown { column: String },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RawEventRange {
+                seconds,
+                max_seconds,
+            } => write!(
+                f,
+                "raw event query over {}s, more than the {}s allowed",
+                seconds, max_seconds
+            ),
+            Self::HighCardinalityBreakdown { column } => {
+                write!(f, "breakdown on high cardinality column {}", column)
+            }
+        }
+    }
+}
+
+/// A query that ran despite tripping a guardrail in [`GuardrailAction::Warn`] mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardrailWarning {
+    pub dataset: String,
+    pub violations: Vec<Violation>,
+}
+
+/// Checks queries for expensive shapes before they are created, to avoid exhausting the
+/// query quota by accident. Clones share the recorded warnings.
+#[derive(Debug, Clone)]
+pub struct Guardrails {
+    pub max_raw_event_range: Option<Duration>,
+    pub high_cardinality_columns: BTreeSet<String>,
+    pub action: GuardrailAction,
+    warnings: Arc<Mutex<Vec<GuardrailWarning>>>,
+}
+
+impl Default for Guardrails {
+    /// Refuse raw event queries over more than 60 days.
+    fn default() -> Self {
+        Self {
+            max_raw_event_range: Some(Duration::from_secs(60 * DAY)),
+            high_cardinality_columns: BTreeSet::new(),
+            action: GuardrailAction::default(),
+            warnings: Arc::default(),
+        }
+    }
+}
+
+impl Guardrails {
+    pub fn with_max_raw_event_range(mut self, max: Option<Duration>) -> Self {
+        self.max_raw_event_range = max;
+        self
+    }
+
+    /// Columns that shouldn't be broken down on, e.g. `trace.trace_id` or `user.id`.
+    pub fn with_high_cardinality_columns<I, S>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.high_cardinality_columns
+            .extend(columns.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_action(mut self, action: GuardrailAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    pub fn violations(&self, spec: &QuerySpec) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        if let (true, Some(max), Some(seconds)) = (
+            spec.calculations.is_empty(),
+            self.max_raw_event_range,
+            spec.range_seconds(),
+        ) {
+            let max_seconds = max.as_secs() as usize;
+            if seconds > max_seconds {
+                violations.push(Violation::RawEventRange {
+                    seconds,
+                    max_seconds,
+                });
+            }
+        }
+        for breakdown in &spec.breakdowns {
+            if self.high_cardinality_columns.contains(&breakdown.0) {
+                violations.push(Violation::HighCardinalityBreakdown {
+                    column: breakdown.0.clone(),
+                });
+            }
+        }
+        violations
+    }
+
+    /// Check the spec, failing or recording a warning as set by the action.
+    pub fn check(&self, dataset_slug: &str, spec: &QuerySpec) -> anyhow::Result<()> {
+        let violations = self.violations(spec);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        match self.action {
+            GuardrailAction::Refuse => Err(GuardrailRefused {
+                dataset: dataset_slug.to_string(),
+                violations,
+            }
+            .into()),
+            GuardrailAction::Warn => {
+                self.warnings.lock().unwrap().push(GuardrailWarning {
+                    dataset: dataset_slug.to_string(),
+                    violations,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// The queries that tripped a guardrail but ran anyway, oldest first.
+    pub fn warnings(&self) -> Vec<GuardrailWarning> {
+        self.warnings.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::Calculation;
+
+    fn raw_events(seconds: usize) -> QuerySpec {
+        QuerySpec::builder().time_range(seconds).build()
+    }
+
+    #[test]
+    fn raw_event_queries_over_the_budget_are_refused() {
+        let guardrails = Guardrails::default();
+        let max_seconds = 60 * DAY as usize;
+        assert!(guardrails
+            .check("checkout", &raw_events(max_seconds))
+            .is_ok());
+        let e = guardrails
+            .check("checkout", &raw_events(max_seconds + 1))
+            .unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<GuardrailRefused>().unwrap().violations,
+            vec![Violation::RawEventRange {
+                seconds: max_seconds + 1,
+                max_seconds
+            }]
+        );
+        // Calculations aren't raw events
+        let spec = QuerySpec::builder()
+            .calc(Calculation::count())
+            .time_range(max_seconds + 1)
+            .build();
+        assert!(guardrails.violations(&spec).is_empty());
+    }
+
+    #[test]
+    fn warn_records_high_cardinality_breakdowns() {
+        let guardrails = Guardrails::default()
+            .with_high_cardinality_columns(["user.id"])
+            .with_action(GuardrailAction::Warn);
+        let spec = QuerySpec::builder()
+            .calc(Calculation::count())
+            .breakdowns(["service.name", "user.id"])
+            .build();
+        assert!(guardrails.clone().check("checkout", &spec).is_ok());
+        assert_eq!(
+            guardrails.warnings(),
+            vec![GuardrailWarning {
+                dataset: "checkout".to_string(),
+                violations: vec![Violation::HighCardinalityBreakdown {
+                    column: "user.id".to_string()
+                }],
+            }]
+        );
+    }
+}
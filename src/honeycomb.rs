@@ -1,7 +1,9 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     env,
     fmt::{Display, Formatter},
+    future::Future,
+    sync::Arc,
 };
 
 use anyhow::Context;
@@ -9,30 +11,144 @@ use chrono::{DateTime, Utc};
 use futures::stream::{self, FuturesOrdered, StreamExt};
 use indicatif::ProgressBar;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use tokio;
+use tokio_util::sync::CancellationToken;
+
+use crate::adaptive::{rate_limit_reset, AdaptiveLimiter, AdaptivePermit};
+use crate::api_enum::api_enum;
+use crate::cache::QueryCache;
+use crate::capabilities::{Capabilities, Capability};
+use crate::circuit_breaker::CircuitBreaker;
+use crate::clock::{Clock, SystemClock};
+use crate::dataset::{DatasetFilter, DatasetSettings, DatasetSlugs, NeverWritten};
+use crate::error::{
+    ApiError, Cancelled, DeleteProtected, DeserializeError, KeyKind, QueryTimeout,
+    QueryValidationError, RetriesExhausted, StatusError, UnknownFields,
+};
+use crate::guardrails::Guardrails;
+use crate::key_pool::KeyPool;
+use crate::progress::Eta;
+use crate::query::{
+    query_hash, Calculation, Filter, Percentile, PollPolicy, QueryHandle, QueryOutput,
+    QueryResultData, QuerySpec, SavedQuery, TimeRange,
+};
+use crate::rate_limit::RateLimiter;
+use crate::report::BulkReport;
+use crate::retry::{
+    is_retryable_status, is_transient, retry_after, RetryPolicy, RetrySnapshot, RetryStats,
+};
+use crate::support::RequestLog;
 
 #[derive(Debug, Clone)]
 pub struct HoneyComb {
     pub api_key: String,
+    pub retry_policy: RetryPolicy,
+    pub base_url: String,
+    pub rate_limiter: Option<RateLimiter>,
+    pub key_pool: Option<KeyPool>,
+    pub circuit_breaker: Option<CircuitBreaker>,
+    pub cancellation: Option<CancellationToken>,
+    pub parse_mode: ParseMode,
+    /// The most result rows to ask for when running a query.
+    pub result_limit: usize,
+    pub poll_policy: PollPolicy,
+    pub query_cache: Option<QueryCache>,
+    pub adaptive_limiter: Option<AdaptiveLimiter>,
+    pub guardrails: Option<Guardrails>,
+    pub clock: Arc<dyn Clock>,
+    pub(crate) retry_stats: Arc<RetryStats>,
+    pub(crate) request_log: Arc<RequestLog>,
+    pub(crate) capabilities: Arc<tokio::sync::OnceCell<Capabilities>>,
+    /// The team and environment the key belongs to, for query cache keys.
+    pub(crate) environment: Arc<tokio::sync::OnceCell<(String, String)>>,
+}
+/// How responses are deserialized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Ignore fields the types don't know about and default missing optional ones.
+    #[default]
+    Lenient,
+    /// Fail with [`UnknownFields`] when a response has fields the types don't know about.
+    Strict,
 }
+
 const URL: &str = "https://api.honeycomb.io/1/";
 const HONEYCOMB_API_KEY: &str = "HONEYCOMB_API_KEY";
+/// The slug for querying every dataset in an environment at once.
+pub const ALL_DATASETS: &str = "__all__";
+/// The column holding a span's trace ID.
+pub const TRACE_ID: &str = "trace.trace_id";
+/// The column holding a span's parent, absent on root spans.
+pub const TRACE_PARENT_ID: &str = "trace.parent_id";
+/// The most result rows the Query Data API returns for one query.
+pub const MAX_RESULT_LIMIT: usize = 10000;
+/// The `api_key_access` entry needed to run queries.
+pub const QUERIES_ACCESS: &str = "queries";
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Dataset {
     pub slug: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub created_at: Option<DateTime<Utc>>,
+    /// `None` for datasets that have been created but never written to.
     pub last_written_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub regular_columns_count: u64,
+    #[serde(default)]
+    pub expand_json_depth: u8,
+    #[serde(default)]
+    pub settings: DatasetSettings,
+}
+
+api_enum! {
+    pub enum ColumnType {
+        String => "string",
+        Float => "float",
+        Integer => "integer",
+        Boolean => "boolean",
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Column {
     pub id: String,
     pub key_name: String,
-    pub r#type: String,
+    pub r#type: ColumnType,
     pub description: String,
     pub hidden: bool,
-    pub last_written: DateTime<Utc>,
+    /// `None` for columns that have been created but never written to.
+    pub last_written: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// A value of a column and the number of events with it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Variant {
+    pub value: String,
+    pub count: u64,
+}
+
+impl Variant {
+    /// Numbers and booleans are kept in their JSON form. Rows for events without the
+    /// column have a null value and are skipped.
+    fn from_row(row: &Map<String, Value>, column_id: &str) -> anyhow::Result<Option<Self>> {
+        let value = match row.get(column_id) {
+            None | Some(Value::Null) => return Ok(None),
+            Some(Value::String(value)) => value.clone(),
+            Some(value @ (Value::Number(_) | Value::Bool(_))) => value.to_string(),
+            Some(value) => anyhow::bail!("unexpected {} value {}", column_id, value),
+        };
+        let count = row
+            .get("COUNT")
+            .and_then(Value::as_u64)
+            .context("missing COUNT")?;
+        Ok(Some(Self { value, count }))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,12 +158,8 @@ struct QueryResultLinks {
 
 #[derive(Debug, Deserialize)]
 struct QueryResult {
-    links: QueryResultLinks,
-}
-
-#[derive(Debug, Deserialize)]
-struct Query {
     id: String,
+    links: QueryResultLinks,
 }
 
 #[derive(Debug, Deserialize)]
@@ -75,6 +187,14 @@ impl Authorizations {
             .iter()
             .all(|access_type| *self.api_key_access.get(*access_type).unwrap_or(&false))
     }
+
+    pub fn missing_access(&self, access_types: &[&str]) -> Vec<String> {
+        access_types
+            .iter()
+            .filter(|access_type| !*self.api_key_access.get(**access_type).unwrap_or(&false))
+            .map(|access_type| access_type.to_string())
+            .collect()
+    }
 }
 
 impl Display for Authorizations {
@@ -93,37 +213,408 @@ impl Display for Authorizations {
 
 impl HoneyComb {
     pub fn new() -> anyhow::Result<Self> {
-        Ok(Self {
-            api_key: env::var(HONEYCOMB_API_KEY).context(format!(
-                "Environment variable {} not found",
-                HONEYCOMB_API_KEY
-            ))?,
-        })
+        Ok(Self::from_api_key(env::var(HONEYCOMB_API_KEY).context(
+            format!("Environment variable {} not found", HONEYCOMB_API_KEY),
+        )?))
+    }
+
+    pub fn from_api_key(api_key: impl Into<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            retry_policy: RetryPolicy::default(),
+            base_url: URL.to_string(),
+            rate_limiter: None,
+            key_pool: None,
+            circuit_breaker: None,
+            cancellation: None,
+            parse_mode: ParseMode::default(),
+            result_limit: MAX_RESULT_LIMIT,
+            poll_policy: PollPolicy::default(),
+            query_cache: None,
+            adaptive_limiter: None,
+            guardrails: None,
+            clock: Arc::new(SystemClock),
+            retry_stats: Arc::new(RetryStats::default()),
+            request_log: Arc::new(RequestLog::default()),
+            capabilities: Arc::new(tokio::sync::OnceCell::new()),
+            environment: Arc::new(tokio::sync::OnceCell::new()),
+        }
+    }
+
+    /// Abort requests, backoff sleeps and polling as soon as the token is cancelled,
+    /// e.g. on Ctrl-C. Cancelled operations fail with [`Cancelled`].
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Run the future unless the client's cancellation token fires first.
+    pub(crate) async fn cancellable<F: Future>(&self, future: F) -> anyhow::Result<F::Output> {
+        match &self.cancellation {
+            Some(token) => tokio::select! {
+                _ = token.cancelled() => Err(Cancelled.into()),
+                output = future => Ok(output),
+            },
+            None => Ok(future.await),
+        }
+    }
+
+    pub(crate) async fn sleep(&self, duration: tokio::time::Duration) -> anyhow::Result<()> {
+        self.cancellable(tokio::time::sleep(duration)).await
+    }
+
+    /// Sleep before a retry, recording it in the retry stats.
+    async fn backoff(&self, delay: tokio::time::Duration, throttled: bool) -> anyhow::Result<()> {
+        self.retry_stats.record(delay, throttled);
+        self.sleep(delay).await
     }
 
-    async fn get<T>(&self, request: &str) -> anyhow::Result<T>
+    /// Totals of the retries made by this client and its clones.
+    pub fn retry_stats(&self) -> RetrySnapshot {
+        self.retry_stats.snapshot()
+    }
+
+    /// Pace query result creation client-side. Clones of the client share the limiter.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Rotate requests across several keys for the same environment instead of `api_key`.
+    pub fn with_key_pool(mut self, key_pool: KeyPool) -> Self {
+        self.key_pool = Some(key_pool);
+        self.environment = Arc::default();
+        self
+    }
+
+    /// Fail fast on endpoints that keep failing rather than retrying them blindly.
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Choose strict parsing to surface API schema drift early, e.g. in CI.
+    pub fn with_parse_mode(mut self, parse_mode: ParseMode) -> Self {
+        self.parse_mode = parse_mode;
+        self
+    }
+
+    /// Replace the system clock, e.g. with a [`FixedClock`](crate::clock::FixedClock) to
+    /// filter datasets and columns as of a past time.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Point the client at a different API host, e.g. `https://api.eu1.honeycomb.io/1/`.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self.environment = Arc::default();
+        self
+    }
+
+    /// Set how requests are retried. Clones share everything else, so a single call can
+    /// be tuned with `hc.clone().with_retry_policy(policy).create_events(..)`.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Ask for at most `limit` result rows, clamped to the API range 1 to
+    /// [`MAX_RESULT_LIMIT`]. Small limits keep exploratory queries quick, e.g.
+    /// `hc.clone().with_result_limit(100)`.
+    pub fn with_result_limit(mut self, limit: usize) -> Self {
+        self.result_limit = limit.clamp(1, MAX_RESULT_LIMIT);
+        self
+    }
+
+    /// Set how long to wait for query results, e.g. longer for queries over wide ranges.
+    pub fn with_poll_policy(mut self, poll_policy: PollPolicy) -> Self {
+        self.poll_policy = poll_policy;
+        self
+    }
+
+    /// Answer repeated queries from a cache instead of running them again.
+    pub fn with_query_cache(mut self, query_cache: QueryCache) -> Self {
+        self.query_cache = Some(query_cache);
+        self
+    }
+
+    /// Pace queries by the API's rate limit feedback, and let bulk operations such as
+    /// [`HoneyComb::get_all_group_by_variants`] run as many at once as it allows. Clones
+    /// share the limiter.
+    pub fn with_adaptive_limiter(mut self, adaptive_limiter: AdaptiveLimiter) -> Self {
+        self.adaptive_limiter = Some(adaptive_limiter);
+        self
+    }
+
+    /// Check queries for expensive shapes before creating them. See [`Guardrails`].
+    pub fn with_guardrails(mut self, guardrails: Guardrails) -> Self {
+        self.guardrails = Some(guardrails);
+        self
+    }
+
+    /// Send a request through the circuit breaker, if there is one.
+    async fn send<T>(
+        &self,
+        method: reqwest::Method,
+        request: &str,
+        json: Option<&Value>,
+    ) -> anyhow::Result<T>
     where
         T: serde::de::DeserializeOwned,
     {
-        let response = reqwest::Client::new()
-            .get(format!("{}{}", URL, request))
-            .header("X-Honeycomb-Team", &self.api_key)
-            .send()
-            .await?;
-        let headers = response.headers().clone();
-        let status = response.status();
-        let text: String = response.text().await?;
-
-        match serde_json::from_str::<T>(&text) {
-            Ok(t) => Ok(t),
-            Err(e) => {
-                eprintln!(
-                    "Invalid response: GET request = {}, \nstatus = {:?}, \nJSON-data = {}, \nheaders = {:?}",
-                    request, status, text, headers
+        let Some(circuit_breaker) = &self.circuit_breaker else {
+            return self.send_with_retries(method, request, json).await;
+        };
+        let endpoint = CircuitBreaker::endpoint(request);
+        let admission = circuit_breaker.check(&endpoint)?;
+        let result = self.send_with_retries(method, request, json).await;
+        match &result {
+            // Dropping the admission lets another request probe
+            Err(e) if e.is::<Cancelled>() => {}
+            Err(e) => admission.record(!CircuitBreaker::is_failure(e)),
+            Ok(_) => admission.record(true),
+        }
+        result
+    }
+
+    /// Send a request, retrying according to the `retry_policy` while the API responds
+    /// with `429 Too Many Requests`, a transient 5xx or the connection fails, and
+    /// deserialize the JSON response.
+    async fn send_with_retries<T>(
+        &self,
+        method: reqwest::Method,
+        request: &str,
+        json: Option<&Value>,
+    ) -> anyhow::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut attempts = 0;
+        let result = self
+            .attempt_requests(method.clone(), request, json, &mut attempts)
+            .await;
+        match result {
+            Err(e) if attempts > 1 && !e.is::<RetriesExhausted>() => Err(e.context(format!(
+                "{} {} failed after {} attempts",
+                method, request, attempts
+            ))),
+            result => result,
+        }
+    }
+
+    async fn attempt_requests<T>(
+        &self,
+        method: reqwest::Method,
+        request: &str,
+        json: Option<&Value>,
+        attempts: &mut u32,
+    ) -> anyhow::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut last_error = String::new();
+        let mut rate_limited = false;
+        for attempt in 0..self.retry_policy.attempts() {
+            *attempts = attempt + 1;
+            rate_limited = false;
+            let (key_index, api_key) = match &self.key_pool {
+                Some(key_pool) => key_pool.next_key(),
+                None => (0, self.api_key.clone()),
+            };
+            let mut builder = reqwest::Client::new()
+                .request(method.clone(), format!("{}{}", self.base_url, request))
+                .header("X-Honeycomb-Team", &api_key);
+            if let Some(json) = json {
+                builder = builder.json(json);
+            }
+            let mut delay = self.retry_policy.delay(attempt);
+            let started = std::time::Instant::now();
+            let response = match self.cancellable(builder.send()).await? {
+                Ok(response) => response,
+                Err(e) => {
+                    self.request_log.record(
+                        format!("{} {}", method, request),
+                        attempt + 1,
+                        started,
+                        None,
+                        Some(e.to_string()),
+                    );
+                    if !is_transient(&e) {
+                        return Err(e.into());
+                    }
+                    last_error = e.to_string();
+                    if attempt + 1 < self.retry_policy.attempts() {
+                        self.backoff(delay, false).await?;
+                    }
+                    continue;
+                }
+            };
+            let status = response.status();
+            self.request_log.record(
+                format!("{} {}", method, request),
+                attempt + 1,
+                started,
+                Some(status.as_u16()),
+                None,
+            );
+
+            match status {
+                reqwest::StatusCode::UNAUTHORIZED => {
+                    return Err(ApiError::Unauthorized {
+                        key_kind: KeyKind::of(&api_key),
+                    }
+                    .into())
+                }
+                reqwest::StatusCode::FORBIDDEN => {
+                    return Err(ApiError::Forbidden {
+                        request: format!("{} {}", method, request),
+                        key_kind: KeyKind::of(&api_key),
+                    }
+                    .into())
+                }
+                _ => {}
+            }
+            rate_limited = status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+            if let Some(limiter) = &self.adaptive_limiter {
+                let pause = match rate_limited {
+                    true => retry_after(response.headers()),
+                    false => None,
+                };
+                let query_started = status.is_success()
+                    && method == reqwest::Method::POST
+                    && request.starts_with("query_results/");
+                limiter.observe(
+                    rate_limited,
+                    query_started,
+                    pause.or_else(|| rate_limit_reset(response.headers())),
+                );
+            }
+            if is_retryable_status(status) {
+                last_error = status.to_string();
+                if let Some(retry_after) = retry_after(response.headers()) {
+                    // A day long Retry-After from a proxy shouldn't park the client
+                    delay = retry_after.min(self.retry_policy.max_delay);
+                }
+                if let Some(key_pool) = &self.key_pool {
+                    if rate_limited {
+                        delay = key_pool.record_throttled(key_index, delay);
+                    }
+                }
+                if attempt + 1 < self.retry_policy.attempts() {
+                    self.backoff(delay, rate_limited).await?;
+                }
+                continue;
+            }
+            let text: String = match self.cancellable(response.text()).await? {
+                Ok(text) => text,
+                Err(e) if is_transient(&e) => {
+                    last_error = e.to_string();
+                    if attempt + 1 < self.retry_policy.attempts() {
+                        self.backoff(delay, false).await?;
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            if status == reqwest::StatusCode::CONFLICT
+                && method == reqwest::Method::DELETE
+                && request.starts_with("datasets/")
+            {
+                return Err(DeleteProtected {
+                    dataset: request.trim_start_matches("datasets/").to_string(),
+                }
+                .into());
+            }
+
+            if status == reqwest::StatusCode::UNPROCESSABLE_ENTITY
+                && request.starts_with("queries/")
+            {
+                self.request_log.sample(
+                    format!("{} {}", method, request),
+                    status.as_u16(),
+                    json,
+                    &text,
+                );
+                return Err(QueryValidationError::from_body(request, &text).into());
+            }
+
+            if !status.is_success() {
+                self.request_log.sample(
+                    format!("{} {}", method, request),
+                    status.as_u16(),
+                    json,
+                    &text,
+                );
+                return Err(StatusError::new(
+                    format!("{} {}", method, request),
+                    status.as_u16(),
+                    &text,
+                )
+                .into());
+            }
+
+            let result = self.parse(&format!("{} {}", method, request), status, &text);
+            if result.is_err() {
+                self.request_log.sample(
+                    format!("{} {}", method, request),
+                    status.as_u16(),
+                    json,
+                    &text,
                 );
-                Err(anyhow::anyhow!("Failed to parse JSON data: {}", e))
             }
+            return result;
         }
+        Err(RetriesExhausted {
+            request: format!("{} {}", method, request),
+            attempts: *attempts,
+            last_error,
+            rate_limited,
+        }
+        .into())
+    }
+
+    /// Deserialize a response body. In strict mode fields not in `T` are an error.
+    fn parse<T>(&self, request: &str, status: reqwest::StatusCode, text: &str) -> anyhow::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        // `204 No Content` deserializes like `null`, e.g. into `()`
+        let text = if text.trim().is_empty() { "null" } else { text };
+        let mut unknown_fields = Vec::new();
+        let mut track = |path: serde_ignored::Path| unknown_fields.push(path.to_string());
+        let deserializer = &mut serde_json::Deserializer::from_str(text);
+        let deserializer = serde_ignored::Deserializer::new(deserializer, &mut track);
+        let t = serde_path_to_error::deserialize::<_, T>(deserializer).map_err(|e| {
+            DeserializeError::new(
+                request.to_string(),
+                status.as_u16(),
+                e.path().to_string(),
+                e.inner().to_string(),
+                text,
+            )
+        })?;
+        if self.parse_mode == ParseMode::Strict && !unknown_fields.is_empty() {
+            return Err(UnknownFields {
+                request: request.to_string(),
+                fields: unknown_fields,
+            }
+            .into());
+        }
+        Ok(t)
+    }
+
+    pub(crate) async fn get<T>(&self, request: &str) -> anyhow::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.send(reqwest::Method::GET, request, None).await
     }
 
     pub async fn list_authorizations(&self) -> anyhow::Result<Authorizations> {
@@ -135,6 +626,29 @@ impl HoneyComb {
     pub async fn list_all_columns(&self, dataset_slug: &str) -> anyhow::Result<Vec<Column>> {
         self.get(&format!("columns/{}", dataset_slug)).await
     }
+    pub async fn get_column(&self, dataset_slug: &str, column_id: &str) -> anyhow::Result<Column> {
+        self.get(&format!("columns/{}/{}", dataset_slug, column_id))
+            .await
+    }
+    /// Resolve many column IDs concurrently without fetching the whole schema.
+    pub async fn get_columns_by_ids(
+        &self,
+        dataset_slug: &str,
+        column_ids: &[String],
+    ) -> anyhow::Result<BulkReport<Column>> {
+        let mut tasks = stream::iter(column_ids.iter().cloned())
+            .map(|column_id| async {
+                let column = self.get_column(dataset_slug, &column_id).await;
+                (column_id, column)
+            })
+            .buffer_unordered(5);
+
+        let mut report = BulkReport::default();
+        while let Some((column_id, column)) = tasks.next().await {
+            report.record(column_id, column);
+        }
+        Ok(report)
+    }
     pub async fn get_query_results(
         &self,
         dataset_slug: &str,
@@ -147,40 +661,50 @@ impl HoneyComb {
         .await
     }
 
-    async fn post<T>(&self, request: &str, json: Value) -> anyhow::Result<T>
+    /// Like [`HoneyComb::get_query_results`] with the rows deserialized into `T`.
+    pub async fn get_query_result_data<T>(
+        &self,
+        dataset_slug: &str,
+        query_result_id: &str,
+    ) -> anyhow::Result<QueryResultData<T>>
     where
         T: serde::de::DeserializeOwned,
     {
-        let mut retries = 12;
-        while retries > 0 {
-            let response = reqwest::Client::new()
-                .post(format!("{}{}", URL, request))
-                .header("X-Honeycomb-Team", &self.api_key)
-                .json(&json)
-                .send()
-                .await?;
-            let status = response.status();
+        self.get(&format!(
+            "query_results/{}/{}",
+            dataset_slug, query_result_id
+        ))
+        .await
+    }
 
-            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
-                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                retries -= 1;
-                continue;
-            }
-            let headers = response.headers().clone();
-            let text: String = response.text().await?;
+    pub(crate) async fn post<T>(&self, request: &str, json: Value) -> anyhow::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.send(reqwest::Method::POST, request, Some(&json)).await
+    }
 
-            return match serde_json::from_str::<T>(&text) {
-                Ok(t) => Ok(t),
-                Err(e) => {
-                    eprintln!(
-                        "Invalid response: POST request = {}, \nstatus = {:?}, \nJSON-data = {}, \nheaders = {:?}",
-                        request, status, text, headers
-                    );
-                    Err(anyhow::anyhow!("Failed to parse JSON data: {}", e))
-                }
-            };
-        }
-        Err(anyhow::anyhow!("Too many retries"))
+    pub(crate) async fn put<T>(&self, request: &str, json: Value) -> anyhow::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.send(reqwest::Method::PUT, request, Some(&json)).await
+    }
+
+    pub(crate) async fn patch<T>(&self, request: &str, json: Value) -> anyhow::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.send(reqwest::Method::PATCH, request, Some(&json))
+            .await
+    }
+
+    /// Delete a resource, ignoring any response body. An ID or slug that doesn't exist
+    /// fails with a [`StatusError`] rather than succeeding.
+    pub(crate) async fn delete(&self, request: &str) -> anyhow::Result<()> {
+        self.send::<Value>(reqwest::Method::DELETE, request, None)
+            .await
+            .map(|_| ())
     }
 
     pub async fn create_events(
@@ -191,188 +715,567 @@ impl HoneyComb {
         self.post(&format!("batch/{}/", dataset_slug), json).await
     }
 
-    async fn get_query_url(
+    /// A saved query, e.g. one referenced from a runbook, to inspect or run again.
+    pub async fn get_query(
         &self,
         dataset_slug: &str,
-        json: Value,
+        query_id: &str,
+    ) -> anyhow::Result<SavedQuery> {
+        self.get(&format!("queries/{}/{}", dataset_slug, query_id))
+            .await
+    }
+
+    /// Save a query, returning its ID and the spec as normalized by the API. The spec is
+    /// checked locally first, failing with [`crate::error::InvalidQuery`] rather than a 422.
+    pub async fn create_query(
+        &self,
+        dataset_slug: &str,
+        spec: &QuerySpec,
+    ) -> anyhow::Result<SavedQuery> {
+        spec.validate()?;
+        self.post(&format!("queries/{}", dataset_slug), spec.to_value())
+            .await
+    }
+
+    /// Create the query, then a result for it, pacing results with the rate limiter.
+    /// The result runs in the background; poll it with [`HoneyComb::wait_for_results`].
+    pub async fn start_query(
+        &self,
+        dataset_slug: &str,
+        spec: &QuerySpec,
         disable_series: bool,
-    ) -> anyhow::Result<String> {
-        let query: Query = self
-            .post(&format!("queries/{}", dataset_slug), json)
-            .await?;
+    ) -> anyhow::Result<QueryHandle> {
+        anyhow::ensure!(
+            (1..=MAX_RESULT_LIMIT).contains(&self.result_limit),
+            "result limit {} is outside the API range 1..={}",
+            self.result_limit,
+            MAX_RESULT_LIMIT
+        );
+        if let Some(guardrails) = &self.guardrails {
+            guardrails.check(dataset_slug, spec)?;
+        }
+        if dataset_slug == ALL_DATASETS {
+            self.require_capability(Capability::EnvironmentQueries)
+                .await?;
+        }
+        let query = self.create_query(dataset_slug, spec).await?;
 
+        if let Some(rate_limiter) = &self.rate_limiter {
+            self.cancellable(rate_limiter.acquire()).await?;
+        }
         let query_result: QueryResult = self
             .post(
                 &format!("query_results/{}", dataset_slug),
                 serde_json::json!({
                   "query_id": query.id,
                   "disable_series": disable_series,
-                  "limit": 10000
+                  "limit": self.result_limit
                 }),
             )
             .await?;
 
-        Ok(query_result.links.query_url)
+        Ok(QueryHandle {
+            dataset: dataset_slug.to_string(),
+            query_id: query.id,
+            result_id: query_result.id,
+            url: query_result.links.query_url,
+        })
+    }
+
+    /// Poll a started query as set by the client's [`PollPolicy`]. A query still running
+    /// at the deadline fails with [`QueryTimeout`].
+    pub async fn wait_for_results<T>(&self, handle: &QueryHandle) -> anyhow::Result<QueryOutput<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let started = tokio::time::Instant::now();
+        loop {
+            let data: QueryOutput<T> = self
+                .get_query_result_data(&handle.dataset, &handle.result_id)
+                .await?;
+            if data.complete {
+                return Ok(data);
+            }
+            let waited = started.elapsed();
+            if waited >= self.poll_policy.deadline {
+                return Err(QueryTimeout {
+                    dataset: handle.dataset.clone(),
+                    query_result_id: handle.result_id.clone(),
+                    waited,
+                }
+                .into());
+            }
+            self.sleep(self.poll_policy.interval).await?;
+        }
+    }
+
+    /// Run a query and wait for its results, see [`HoneyComb::wait_for_results`]. With a
+    /// query cache, a cached result is returned instead if there is one.
+    pub async fn run_query<T>(
+        &self,
+        dataset_slug: &str,
+        spec: &QuerySpec,
+    ) -> anyhow::Result<QueryOutput<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let Some(cache) = &self.query_cache else {
+            return self.run_uncached(dataset_slug, spec).await;
+        };
+        let now = self.now();
+        let key = cache.key(self.cache_scope().await?, dataset_slug, spec, now);
+        let body = match cache.get(&key, now) {
+            Some(body) => body,
+            None => {
+                let output: QueryOutput<Value> = self.run_uncached(dataset_slug, spec).await?;
+                let body = serde_json::to_value(output)?;
+                cache.put(&key, now, body.clone());
+                body
+            }
+        };
+        self.parse(
+            &format!("cached query_results/{}", dataset_slug),
+            reqwest::StatusCode::OK,
+            &body.to_string(),
+        )
+    }
+
+    /// What, besides the dataset and spec, decides a cached query's result: the API host,
+    /// the team and environment, looked up once per client, and the result limit.
+    async fn cache_scope(&self) -> anyhow::Result<u64> {
+        let (team, environment) = self
+            .environment
+            .get_or_try_init(|| async {
+                let auth = self.list_authorizations().await?;
+                Ok::<_, anyhow::Error>((auth.team.slug, auth.environment.slug))
+            })
+            .await?;
+        Ok(query_hash(&serde_json::json!({
+            "base_url": self.base_url,
+            "team": team,
+            "environment": environment,
+            "limit": self.result_limit,
+        })))
+    }
+
+    /// Start a query and wait for it, holding an adaptive limiter slot throughout.
+    async fn run_uncached<T>(
+        &self,
+        dataset_slug: &str,
+        spec: &QuerySpec,
+    ) -> anyhow::Result<QueryOutput<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let _permit = self.query_slot().await?;
+        let handle = self.start_query(dataset_slug, spec, false).await?;
+        self.wait_for_results(&handle).await
+    }
+
+    /// Wait for a slot from the adaptive limiter, if there is one, to hold while a query
+    /// runs.
+    pub(crate) async fn query_slot(&self) -> anyhow::Result<Option<AdaptivePermit>> {
+        match &self.adaptive_limiter {
+            Some(limiter) => Ok(Some(self.cancellable(limiter.acquire()).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// How many queries bulk operations run at once: the adaptive limiter's maximum, which
+    /// it then paces, or a fixed 3.
+    pub(crate) fn query_concurrency(&self) -> usize {
+        self.adaptive_limiter
+            .as_ref()
+            .map_or(3, AdaptiveLimiter::max)
+    }
+
+    /// Run the same query on many datasets concurrently, sharing the client's rate limit.
+    /// Results are tagged with their dataset, in the order they complete.
+    pub async fn run_query_across<T>(
+        &self,
+        datasets: &[String],
+        spec: &QuerySpec,
+    ) -> anyhow::Result<BulkReport<(String, QueryOutput<T>)>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut tasks =
+            stream::iter(datasets)
+                .map(|dataset_slug| async move {
+                    (dataset_slug, self.run_query(dataset_slug, spec).await)
+                })
+                .buffer_unordered(self.query_concurrency());
+
+        let mut report = BulkReport::default();
+        while let Some((dataset_slug, output)) = tasks.next().await {
+            let result = output.map(|output| (dataset_slug.clone(), output));
+            report.record(dataset_slug.clone(), result);
+        }
+        Ok(report)
     }
 
+    /// Count events with `column_id` set in `time_range`, e.g. a deploy window or a long
+    /// range for a deprecation audit.
     pub async fn get_exists_query_url(
         &self,
         dataset_slug: &str,
         column_id: &str,
+        time_range: TimeRange,
+        disable_series: bool,
+    ) -> anyhow::Result<QueryHandle> {
+        self.start_query(
+            dataset_slug,
+            &QuerySpec::builder()
+                .breakdown(column_id)
+                .calc(Calculation::count())
+                .filter(Filter::exists(column_id))
+                .range(time_range)
+                .build(),
+            disable_series,
+        )
+        .await
+    }
+
+    /// Count events without `column_id`, the gaps in its coverage.
+    pub async fn get_not_exists_query_url(
+        &self,
+        dataset_slug: &str,
+        column_id: &str,
+        time_range: TimeRange,
         disable_series: bool,
-    ) -> anyhow::Result<String> {
-        self.get_query_url(
+    ) -> anyhow::Result<QueryHandle> {
+        self.start_query(
             dataset_slug,
-            serde_json::json!({
-                "breakdowns": [column_id],
-                "calculations": [{
-                    "op": "COUNT"
-                }],
-                "filters": [{
-                    "column": column_id,
-                    "op": "exists",
-                }],
-                "time_range": 604799
-            }),
+            &QuerySpec::builder()
+                .calc(Calculation::count())
+                .filter(Filter::does_not_exist(column_id))
+                .range(time_range)
+                .build(),
             disable_series,
         )
         .await
     }
 
+    /// The average of `column_id` over events matching all `filters`, e.g. one service.
     pub async fn get_avg_query_url(
         &self,
         dataset_slug: &str,
         column_id: &str,
-    ) -> anyhow::Result<String> {
-        self.get_query_url(
+        filters: &[Filter],
+        time_range: TimeRange,
+    ) -> anyhow::Result<QueryHandle> {
+        self.start_query(
+            dataset_slug,
+            &QuerySpec::builder()
+                .calc(Calculation::avg(column_id))
+                .filters(filters.iter().cloned())
+                .range(time_range)
+                .build(),
+            false,
+        )
+        .await
+    }
+
+    /// A percentile of `column_id`, e.g. P99 of `duration_ms`.
+    pub async fn get_percentile_query_url(
+        &self,
+        dataset_slug: &str,
+        column_id: &str,
+        percentile: Percentile,
+        time_range: TimeRange,
+    ) -> anyhow::Result<QueryHandle> {
+        self.start_query(
+            dataset_slug,
+            &QuerySpec::builder()
+                .calc(Calculation::new(percentile, Some(column_id)))
+                .range(time_range)
+                .build(),
+            false,
+        )
+        .await
+    }
+
+    /// The distribution of `column_id` over time, optionally broken down e.g. by
+    /// `service.name`.
+    pub async fn get_heatmap_query_url(
+        &self,
+        dataset_slug: &str,
+        column_id: &str,
+        breakdown: Option<&str>,
+        time_range: TimeRange,
+    ) -> anyhow::Result<QueryHandle> {
+        self.start_query(
+            dataset_slug,
+            &QuerySpec::builder()
+                .calc(Calculation::heatmap(column_id))
+                .breakdowns(breakdown)
+                .range(time_range)
+                .build(),
+            false,
+        )
+        .await
+    }
+
+    /// The number of spans in progress at once, optionally broken down e.g. by `service.name`.
+    pub async fn get_concurrency_query_url(
+        &self,
+        dataset_slug: &str,
+        breakdowns: &[String],
+    ) -> anyhow::Result<QueryHandle> {
+        self.start_query(
+            dataset_slug,
+            &QuerySpec::builder()
+                .breakdowns(breakdowns.iter().cloned())
+                .calc(Calculation::concurrency())
+                .range(TimeRange::Last7Days)
+                .build(),
+            false,
+        )
+        .await
+    }
+
+    /// Count root spans only, i.e. traces, by filtering on spans without a parent.
+    pub async fn get_root_span_count_query_url(
+        &self,
+        dataset_slug: &str,
+        breakdowns: &[String],
+    ) -> anyhow::Result<QueryHandle> {
+        self.start_query(
             dataset_slug,
-            serde_json::json!({
-                "calculations": [{
-                    "op": "AVG",
-                    "column": column_id
-                }],
-                "time_range": 604799
-            }),
+            &QuerySpec::builder()
+                .breakdowns(breakdowns.iter().cloned())
+                .calc(Calculation::count())
+                .filter(Filter::does_not_exist(TRACE_PARENT_ID))
+                .range(TimeRange::Last7Days)
+                .build(),
             false,
         )
         .await
     }
 
+    /// Sample events matching all `filters`, deserialized into `T`. The query has no
+    /// calculations or breakdowns, so each result row is an event; at most the client's
+    /// `result_limit` are returned.
+    pub async fn get_raw_events<T>(
+        &self,
+        dataset_slug: &str,
+        filters: &[Filter],
+        time_range: TimeRange,
+    ) -> anyhow::Result<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let output: QueryOutput<T> = self
+            .run_query(
+                dataset_slug,
+                &QuerySpec::builder()
+                    .filters(filters.iter().cloned())
+                    .range(time_range)
+                    .build(),
+            )
+            .await?;
+        Ok(output.results)
+    }
+
+    /// The number of distinct values of `column_id`, as estimated by `COUNT_DISTINCT`, or
+    /// 0 if there were no events. A result row without an integer count is an error.
+    pub async fn get_cardinality(
+        &self,
+        dataset_slug: &str,
+        column_id: &str,
+        time_range: TimeRange,
+    ) -> anyhow::Result<u64> {
+        let output: QueryOutput = self
+            .run_query(
+                dataset_slug,
+                &QuerySpec::builder()
+                    .calc(Calculation::count_distinct(column_id))
+                    .range(time_range)
+                    .build(),
+            )
+            .await?;
+        let key = format!("COUNT_DISTINCT({})", column_id);
+        let Some(row) = output.results.first() else {
+            return Ok(0);
+        };
+        row.get(&key).and_then(Value::as_u64).with_context(|| {
+            format!(
+                "no integer {} in the result for {}: {}",
+                key,
+                dataset_slug,
+                Value::Object(row.clone())
+            )
+        })
+    }
+
+    /// The values of `column_id` seen in the last `range_seconds` (at most 7 days), with
+    /// the number of events for each, most frequent first.
     pub async fn get_group_by_variants(
         &self,
         dataset_slug: &str,
         column_id: &str,
         range_seconds: usize,
+    ) -> anyhow::Result<Vec<Variant>> {
+        self.query_variants(dataset_slug, column_id, range_seconds, None)
+            .await
+    }
+
+    /// Only the `top` most frequent values of `column_id`, so high-cardinality columns
+    /// don't return thousands of rows.
+    pub async fn get_top_group_by_variants(
+        &self,
+        dataset_slug: &str,
+        column_id: &str,
+        range_seconds: usize,
+        top: usize,
+    ) -> anyhow::Result<Vec<Variant>> {
+        self.query_variants(dataset_slug, column_id, range_seconds, Some(top))
+            .await
+    }
+
+    /// Up to `n` of the most common values of `column_id` in the last day, to show what
+    /// the column contains.
+    pub async fn sample_values(
+        &self,
+        dataset_slug: &str,
+        column_id: &str,
+        n: usize,
     ) -> anyhow::Result<Vec<String>> {
-        let url = self
-            .get_query_url(
+        let variants = self
+            .get_top_group_by_variants(
                 dataset_slug,
-                serde_json::json!({
-                    "breakdowns": [column_id],
-                    "calculations": [{
-                        "op": "COUNT"
-                    }],
-                    "time_range": 604799.min(range_seconds)
-                }),
-                false,
+                column_id,
+                TimeRange::Last24Hours.seconds(),
+                n.max(1),
             )
             .await?;
-        let token = url.split('/').last().context("Invalid query URL")?;
-        let mut results = Vec::new();
-        let mut polls = 50; // ~5 seconds
-        while polls > 0 {
-            let value = self.get_query_results(dataset_slug, token).await?;
-            if value["complete"]
-                .as_bool()
-                .context("Missing 'complete' field")?
+        Ok(variants.into_iter().take(n).map(|v| v.value).collect())
+    }
+
+    async fn query_variants(
+        &self,
+        dataset_slug: &str,
+        column_id: &str,
+        range_seconds: usize,
+        top: Option<usize>,
+    ) -> anyhow::Result<Vec<Variant>> {
+        let mut spec = QuerySpec::builder()
+            .breakdown(column_id)
+            .top_by(Calculation::count())
+            .time_range(TimeRange::Last7Days.seconds().min(range_seconds));
+        if let Some(top) = top {
+            spec = spec.limit(top);
+        }
+        let output: QueryOutput = self.run_query(dataset_slug, &spec.build()).await?;
+        let mut variants = Vec::new();
+        for row in &output.results {
+            if let Some(variant) = Variant::from_row(row, column_id)
+                .with_context(|| format!("Malformed result row in {}: {:?}", dataset_slug, row))?
             {
-                for r in value["data"]["results"].as_array().unwrap_or(&vec![]) {
-                    if let Some(column) = r["data"][column_id].as_str() {
-                        results.push(column.to_string());
-                    }
-                }
-                break;
+                variants.push(variant);
             }
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-            polls -= 1;
         }
-        Ok(results)
+        Ok(variants)
     }
 
-    /// Get a list of datasets that have been written to in the last `last_written` days
+    /// Get a list of datasets that have been written to in the last `last_written` days.
+    /// Datasets never written to are included; see [`HoneyComb::list_dataset_slugs`] to
+    /// leave them out or list them separately.
     pub async fn get_dataset_slugs(
         &self,
         last_written: i64,
         include_datasets: Option<HashSet<String>>,
     ) -> anyhow::Result<Vec<String>> {
-        let inc_datasets = include_datasets.unwrap_or_default();
-        let now = Utc::now();
-        let mut datasets = self
-            .list_all_datasets()
-            .await?
-            .iter()
-            .filter_map(|d| {
-                if (now - d.last_written_at.unwrap_or(now)).num_days() < last_written {
-                    if inc_datasets.is_empty() || inc_datasets.contains(&d.slug) {
-                        Some(d.slug.clone())
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
-        datasets.sort();
+        let filter = DatasetFilter::default().include(include_datasets.unwrap_or_default());
+        self.get_dataset_slugs_matching(last_written, &filter).await
+    }
+
+    /// Like [`HoneyComb::get_dataset_slugs`], choosing datasets with include and exclude
+    /// patterns. Never-written datasets are in the list unless the filter says otherwise.
+    pub async fn get_dataset_slugs_matching(
+        &self,
+        last_written: i64,
+        filter: &DatasetFilter,
+    ) -> anyhow::Result<Vec<String>> {
+        Ok(self.list_dataset_slugs(last_written, filter).await?.active)
+    }
 
-        Ok(datasets)
+    /// The datasets matching `filter` written to in the last `last_written` days, with
+    /// never-written datasets treated as the filter says.
+    pub async fn list_dataset_slugs(
+        &self,
+        last_written: i64,
+        filter: &DatasetFilter,
+    ) -> anyhow::Result<DatasetSlugs> {
+        let now = self.now();
+        let mut slugs = DatasetSlugs::default();
+        for dataset in self.list_all_datasets().await? {
+            if !filter.matches(&dataset.slug) {
+                continue;
+            }
+            match (dataset.last_written_at, filter.never_written) {
+                (Some(written), _) if (now - written).num_days() < last_written => {
+                    slugs.active.push(dataset.slug)
+                }
+                (None, NeverWritten::Include) if last_written > 0 => {
+                    slugs.active.push(dataset.slug)
+                }
+                (Some(_), _) | (None, NeverWritten::Include | NeverWritten::Exclude) => {}
+                (None, NeverWritten::Separate) => slugs.never_written.push(dataset.slug),
+            }
+        }
+        slugs.active.sort();
+        slugs.never_written.sort();
+        Ok(slugs)
     }
 
     /// Process datasets and columns in parallel and call the provided function for each dataset.
     /// The order of the datasets is preserved. Only columns that have been written to in the last
-    /// `last_written` days are processed.
+    /// `last_written` days are processed, so columns never written to are left out. Datasets
+    /// whose columns could not be fetched are reported as failed rather than passed to the function.
     pub async fn process_datasets_columns<F>(
         &self,
         last_written: i64,
-        datasets: &Vec<String>,
+        datasets: &[String],
         mut f: F,
-    ) -> anyhow::Result<()>
+    ) -> anyhow::Result<BulkReport<String>>
     where
         F: FnMut(String, Vec<Column>),
     {
-        let now = Utc::now();
+        let now = self.now();
         let mut tasks = FuturesOrdered::new();
 
         for dataset in datasets {
             let dataset_clone = dataset.clone();
             let hc_clone = self.clone();
             tasks.push_back(async move {
-                let columns = hc_clone.list_all_columns(&dataset_clone).await;
-                match columns {
-                    Ok(columns) => (
-                        dataset_clone,
+                let columns = hc_clone
+                    .list_all_columns(&dataset_clone)
+                    .await
+                    .map(|columns| {
                         columns
                             .iter()
-                            .filter(|&c| (now - c.last_written).num_days() < last_written)
+                            .filter(|&c| {
+                                c.last_written
+                                    .is_some_and(|w| (now - w).num_days() < last_written)
+                            })
                             .cloned()
-                            .collect(),
-                    ),
-                    Err(e) => {
-                        eprintln!(
-                            "error fetching columns for dataset {}: {}",
-                            dataset_clone, e
-                        );
-                        (dataset_clone, vec![])
-                    }
-                }
+                            .collect::<Vec<_>>()
+                    });
+                (dataset_clone, columns)
             });
         }
 
+        let mut report = BulkReport::default();
         while let Some((dataset, columns)) = tasks.next().await {
-            f(dataset, columns);
+            let result = columns.map(|columns| {
+                f(dataset.clone(), columns);
+                dataset.clone()
+            });
+            report.record(dataset, result);
         }
 
-        Ok(())
+        Ok(report)
     }
 
     pub async fn get_all_group_by_variants(
@@ -380,37 +1283,64 @@ impl HoneyComb {
         dataset_slug: &str,
         columns_ids: &[String],
         range_seconds: usize,
-    ) -> anyhow::Result<Vec<(String, Vec<String>)>> {
-        let bar = ProgressBar::new(columns_ids.len() as u64)
+    ) -> anyhow::Result<BulkReport<(String, Vec<Variant>)>> {
+        let datasets = [(dataset_slug.to_string(), columns_ids.to_vec())];
+        let mut reports = self
+            .get_datasets_group_by_variants(&datasets, range_seconds)
+            .await?;
+        Ok(reports.remove(dataset_slug).unwrap_or_default())
+    }
+
+    /// Like [`HoneyComb::get_all_group_by_variants`] for many `(dataset, columns)` pairs at
+    /// once. All queries share one queue and one progress bar, so the rate limit is spread
+    /// across datasets rather than each dataset waiting for the previous one to finish.
+    pub async fn get_datasets_group_by_variants(
+        &self,
+        datasets: &[(String, Vec<String>)],
+        range_seconds: usize,
+    ) -> anyhow::Result<BTreeMap<String, BulkReport<(String, Vec<Variant>)>>> {
+        let total = datasets
+            .iter()
+            .map(|(_, columns)| columns.len())
+            .sum::<usize>();
+        let bar = ProgressBar::new(total as u64)
             .with_style(
                 indicatif::ProgressStyle::default_bar()
                     .template("[{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")?,
             )
             .with_message("Rate-limited queries, please wait...");
         bar.inc(0);
+        let mut eta = Eta::new(self, total);
 
-        let mut tasks = stream::iter(columns_ids.iter().cloned())
-            .map(|column_id| async {
+        let mut reports = datasets
+            .iter()
+            .map(|(dataset_slug, _)| (dataset_slug.clone(), BulkReport::default()))
+            .collect::<BTreeMap<_, _>>();
+        let pairs = datasets.iter().flat_map(|(dataset_slug, columns)| {
+            columns
+                .iter()
+                .map(move |column_id| (dataset_slug.as_str(), column_id.as_str()))
+        });
+        let mut tasks = stream::iter(pairs)
+            .map(|(dataset_slug, column_id)| async move {
                 let variants = self
-                    .get_group_by_variants(dataset_slug, &column_id, range_seconds)
+                    .get_group_by_variants(dataset_slug, column_id, range_seconds)
                     .await;
-                match variants {
-                    Ok(variants) => (column_id, variants),
-                    Err(e) => {
-                        eprintln!("error fetching variants for column {}: {}", column_id, e);
-                        (column_id, vec![])
-                    }
-                }
+                (dataset_slug, column_id, variants)
             })
-            .buffer_unordered(3);
+            .buffer_unordered(self.query_concurrency());
 
-        let mut results = Vec::new();
-        while let Some(result) = tasks.next().await {
+        while let Some((dataset_slug, column_id, variants)) = tasks.next().await {
             bar.inc(1);
-            results.push(result);
+            eta.complete();
+            bar.set_message(eta.message(self));
+            let result = variants.map(|variants| (column_id.to_string(), variants));
+            if let Some(report) = reports.get_mut(dataset_slug) {
+                report.record(column_id.to_string(), result);
+            }
         }
         bar.finish_and_clear();
 
-        Ok(results)
+        Ok(reports)
     }
 }
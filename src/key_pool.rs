@@ -0,0 +1,125 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::error::KeyKind;
+
+/// Request counters for one key in a [`KeyPool`].
+#[derive(Debug, Clone, Default)]
+pub struct KeyStats {
+    pub requests: u64,
+    pub throttled: u64,
+    pub throttled_until: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct PooledKey {
+    api_key: String,
+    stats: Mutex<KeyStats>,
+}
+
+/// Several API keys for the same environment used round-robin. A key that is rate
+/// limited is rested until its `Retry-After` passes while the others carry on.
+/// Clones of the pool share the rotation and the stats.
+#[derive(Debug, Clone)]
+pub struct KeyPool {
+    keys: Arc<Vec<PooledKey>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl KeyPool {
+    pub fn new(api_keys: Vec<String>) -> anyhow::Result<Self> {
+        if api_keys.is_empty() {
+            anyhow::bail!("A key pool needs at least one API key");
+        }
+        Ok(Self {
+            keys: Arc::new(
+                api_keys
+                    .into_iter()
+                    .map(|api_key| PooledKey {
+                        api_key,
+                        stats: Mutex::new(KeyStats::default()),
+                    })
+                    .collect(),
+            ),
+            next: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Pick the next key that isn't resting, or the one that will be available soonest.
+    pub(crate) fn next_key(&self) -> (usize, String) {
+        let now = Instant::now();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut soonest: Option<(usize, Instant)> = None;
+        for offset in 0..self.keys.len() {
+            let index = (start + offset) % self.keys.len();
+            let mut stats = self.keys[index].stats.lock().unwrap();
+            match stats.throttled_until {
+                Some(until) if until > now => {
+                    if soonest.is_none_or(|(_, s)| until < s) {
+                        soonest = Some((index, until));
+                    }
+                }
+                _ => {
+                    stats.requests += 1;
+                    return (index, self.keys[index].api_key.clone());
+                }
+            }
+        }
+        let index = soonest.map(|(index, _)| index).unwrap_or_default();
+        self.keys[index].stats.lock().unwrap().requests += 1;
+        (index, self.keys[index].api_key.clone())
+    }
+
+    /// Rest a key that was rate limited and return how long until any key is available.
+    pub(crate) fn record_throttled(&self, index: usize, delay: Duration) -> Duration {
+        let now = Instant::now();
+        {
+            let mut stats = self.keys[index].stats.lock().unwrap();
+            stats.throttled += 1;
+            stats.throttled_until = Some(now + delay);
+        }
+        self.keys
+            .iter()
+            .map(|k| {
+                k.stats
+                    .lock()
+                    .unwrap()
+                    .throttled_until
+                    .map(|until| until.saturating_duration_since(now))
+                    .unwrap_or_default()
+            })
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// The kind shared by every key in the pool, or [`KeyKind::Unknown`] if they differ.
+    pub fn key_kind(&self) -> KeyKind {
+        let mut kinds = self.keys.iter().map(|k| KeyKind::of(&k.api_key));
+        let first = kinds.next().unwrap_or(KeyKind::Unknown);
+        match kinds.all(|kind| kind == first) {
+            true => first,
+            false => KeyKind::Unknown,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Stats for each key in the order they were given.
+    pub fn stats(&self) -> Vec<KeyStats> {
+        self.keys
+            .iter()
+            .map(|k| k.stats.lock().unwrap().clone())
+            .collect()
+    }
+}
@@ -1,21 +1,58 @@
+pub mod adaptive;
+pub mod annotations;
+mod api_enum;
+pub mod audit;
+pub mod board;
+pub mod cache;
+pub mod capabilities;
+pub mod chunked;
+pub mod circuit_breaker;
+pub mod clock;
+pub mod column;
+pub mod dataset;
+pub mod definitions;
+pub mod descriptions;
+pub mod diff;
+pub mod drift;
+pub mod duration;
+pub mod error;
+pub mod export;
+pub mod guardrails;
 pub mod honeycomb;
+pub mod key_pool;
+#[cfg(feature = "test-util")]
+pub mod mock;
+pub mod paginate;
+pub mod pii;
+mod progress;
+pub mod query;
+pub mod query_annotation;
+pub mod rate_limit;
+pub mod report;
+pub mod retry;
+pub mod scheduler;
+pub mod schema;
+pub mod schema_diff;
+pub mod snapshot;
+pub mod support;
+pub mod trace;
+pub mod trigger;
+pub mod validate;
 
-pub async fn get_honeycomb(
-    required_access: &[&str],
-) -> anyhow::Result<Option<honeycomb::HoneyComb>> {
-    match honeycomb::HoneyComb::new() {
-        Ok(hc) => {
-            let auth = hc.list_authorizations().await?;
-            if auth.has_required_access(required_access) {
-                Ok(Some(hc))
-            } else {
-                eprintln!(
-                    "honeycomb: missing required access {:?}:\n{}",
-                    required_access, auth
-                );
-                Ok(None)
-            }
+/// Create a client from the environment and check the key has all of `required_access`.
+/// A key without it gives an [`error::MissingAccess`] error carrying the missing access
+/// types and the key's authorizations.
+pub async fn get_honeycomb(required_access: &[&str]) -> anyhow::Result<honeycomb::HoneyComb> {
+    let hc = honeycomb::HoneyComb::new()?;
+    let authorizations = hc.list_authorizations().await?;
+    let missing = authorizations.missing_access(required_access);
+    if missing.is_empty() {
+        Ok(hc)
+    } else {
+        Err(error::MissingAccess {
+            missing,
+            authorizations,
         }
-        Err(e) => Err(e),
+        .into())
     }
 }
@@ -0,0 +1,213 @@
+//! A local stand-in for the Honeycomb API so that downstream tools can test their
+//! pacing and error handling without a real key. Enabled with the `test-util` feature.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::honeycomb::HoneyComb;
+
+/// Allow `requests` within any sliding window of `per`. Requests over the limit get a
+/// `429 Too Many Requests` with a `Retry-After` header in whole seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub requests: usize,
+    pub per: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+    pub throttled: bool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    routes: HashMap<(String, String), (u16, String)>,
+    rate_limit: Option<RateLimit>,
+    window: VecDeque<Instant>,
+    requests: Vec<RecordedRequest>,
+    errors: Vec<String>,
+}
+
+impl State {
+    /// Returns the delay to report in `Retry-After` if this request is over the limit.
+    fn throttle(&mut self, now: Instant) -> Option<Duration> {
+        let limit = self.rate_limit?;
+        while let Some(&oldest) = self.window.front() {
+            if now.duration_since(oldest) >= limit.per {
+                self.window.pop_front();
+            } else {
+                break;
+            }
+        }
+        if self.window.len() >= limit.requests {
+            // With a limit of 0 the window is empty and every request waits a full period
+            let retry_in = match self.window.front() {
+                Some(&oldest) => limit.per - now.duration_since(oldest),
+                None => limit.per,
+            };
+            return Some(retry_in);
+        }
+        self.window.push_back(now);
+        None
+    }
+}
+
+/// A minimal HTTP server listening on localhost that answers with canned responses.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let server_state = state.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, state.clone()).await {
+                        state.lock().unwrap().errors.push(e.to_string());
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            addr,
+            state,
+            handle,
+        })
+    }
+
+    /// The base URL to give to [`HoneyComb::with_base_url`].
+    pub fn url(&self) -> String {
+        format!("http://{}/1/", self.addr)
+    }
+
+    /// A client pointed at this server.
+    pub fn client(&self) -> HoneyComb {
+        HoneyComb::from_api_key("mock-api-key").with_base_url(self.url())
+    }
+
+    pub fn set_rate_limit(&self, rate_limit: Option<RateLimit>) {
+        let mut state = self.state.lock().unwrap();
+        state.rate_limit = rate_limit;
+        state.window.clear();
+    }
+
+    /// Respond to `method` requests for `path` (relative to the base URL, ignoring any
+    /// query string) with the given status and JSON body.
+    pub fn mock(&self, method: &str, path: &str, status: u16, body: Value) {
+        self.state.lock().unwrap().routes.insert(
+            (method.to_uppercase(), path.trim_matches('/').to_string()),
+            (status, body.to_string()),
+        );
+    }
+
+    /// All requests received so far, including throttled ones.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Connections that failed, e.g. because the client hung up mid-request.
+    pub fn errors(&self) -> Vec<String> {
+        self.state.lock().unwrap().errors.clone()
+    }
+
+    pub fn throttled_count(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|r| r.throttled)
+            .count()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn handle_connection(stream: TcpStream, state: Arc<Mutex<State>>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let path = target
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches("/1/")
+        .trim_matches('/')
+        .to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+
+    let (status, extra_headers, response_body) = {
+        let mut state = state.lock().unwrap();
+        let throttle = state.throttle(Instant::now());
+        state.requests.push(RecordedRequest {
+            method: method.clone(),
+            path: path.clone(),
+            body: String::from_utf8_lossy(&body).to_string(),
+            throttled: throttle.is_some(),
+        });
+        match throttle {
+            Some(delay) => (
+                429,
+                format!("Retry-After: {}\r\n", delay.as_secs_f64().ceil() as u64),
+                r#"{"error":"rate limit exceeded"}"#.to_string(),
+            ),
+            None => match state.routes.get(&(method, path)) {
+                Some((status, body)) => (*status, String::new(), body.clone()),
+                None => (404, String::new(), r#"{"error":"not found"}"#.to_string()),
+            },
+        }
+    };
+
+    let response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+        status,
+        response_body.len(),
+        extra_headers,
+        response_body
+    );
+    let mut stream = reader.into_inner();
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,56 @@
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use serde::Deserialize;
+
+use crate::honeycomb::{Dataset, HoneyComb};
+
+#[derive(Debug, Deserialize)]
+struct PageLinks {
+    next: Option<String>,
+}
+
+/// A list response: either every item at once, or a page of them with a link to the
+/// next page, JSON:API style.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Page<T> {
+    All(Vec<T>),
+    Paged {
+        data: Vec<T>,
+        links: Option<PageLinks>,
+    },
+}
+
+impl HoneyComb {
+    /// Fetch a list page by page, yielding items as each page arrives. Next links may be
+    /// absolute URLs or paths relative to the API root.
+    pub(crate) fn paginate<T>(&self, request: String) -> impl Stream<Item = anyhow::Result<T>> + '_
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        stream::try_unfold(Some(request), move |request| async move {
+            let Some(request) = request else {
+                return Ok(None);
+            };
+            let (items, next) = match self.get::<Page<T>>(&request).await? {
+                Page::All(items) => (items, None),
+                Page::Paged { data, links } => (data, links.and_then(|l| l.next)),
+            };
+            let next = next.map(|next| {
+                next.strip_prefix(&self.base_url)
+                    .or_else(|| next.strip_prefix("/1/"))
+                    .unwrap_or(&next)
+                    .trim_start_matches('/')
+                    .to_string()
+            });
+            Ok::<_, anyhow::Error>(Some((stream::iter(items.into_iter().map(Ok)), next)))
+        })
+        .try_flatten()
+        .boxed()
+    }
+
+    /// Datasets as they are fetched, so very large environments can be processed without
+    /// holding every dataset in memory.
+    pub fn list_datasets_stream(&self) -> impl Stream<Item = anyhow::Result<Dataset>> + '_ {
+        self.paginate("datasets".to_string())
+    }
+}
@@ -0,0 +1,274 @@
+use std::net::IpAddr;
+
+use serde::Serialize;
+
+use crate::{
+    honeycomb::{Column, ColumnType, HoneyComb, QUERIES_ACCESS},
+    report::ReportSection,
+};
+
+/// Key name segments that suggest a column holds personal data.
+const PII_KEY_SEGMENTS: &[&str] = &[
+    "email",
+    "e-mail",
+    "phone",
+    "mobile",
+    "ssn",
+    "password",
+    "passwd",
+    "firstname",
+    "first_name",
+    "lastname",
+    "last_name",
+    "fullname",
+    "full_name",
+    "username",
+    "dob",
+    "birthdate",
+    "date_of_birth",
+    "address",
+    "postcode",
+    "zipcode",
+    "ip",
+    "credit_card",
+    "card_number",
+    "iban",
+];
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum PiiKind {
+    Email,
+    IpAddress,
+    PhoneNumber,
+    CreditCard,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub enum PiiReason {
+    /// The key name contains a suspicious segment.
+    KeyName(String),
+    /// Sampled values look like personal data.
+    ValueShape {
+        kind: PiiKind,
+        matched: usize,
+        sampled: usize,
+    },
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PiiFinding {
+    pub dataset: String,
+    pub key_name: String,
+    pub reasons: Vec<PiiReason>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PiiReport {
+    pub findings: Vec<PiiFinding>,
+    /// The number of string columns whose values were sampled.
+    pub value_sampling: ReportSection<usize>,
+}
+
+/// Returns the suspicious segment if the key name looks like it holds personal data.
+/// Key names are split on `.` so `client.address` matches on `address` but
+/// `http.route` does not match on anything.
+pub fn pii_key_segment(key_name: &str) -> Option<&'static str> {
+    let key_name = key_name.to_lowercase();
+    key_name.split('.').find_map(|segment| {
+        PII_KEY_SEGMENTS.iter().copied().find(|&p| {
+            segment == p
+                || segment.ends_with(&format!("_{}", p))
+                || segment.starts_with(&format!("{}_", p))
+        })
+    })
+}
+
+/// Classify a single value by its shape.
+pub fn pii_value_kind(value: &str) -> Option<PiiKind> {
+    let value = value.trim();
+    if is_email(value) {
+        Some(PiiKind::Email)
+    } else if value.parse::<IpAddr>().is_ok() {
+        Some(PiiKind::IpAddress)
+    } else if is_credit_card(value) {
+        Some(PiiKind::CreditCard)
+    } else if is_phone_number(value) {
+        Some(PiiKind::PhoneNumber)
+    } else {
+        None
+    }
+}
+
+fn is_email(value: &str) -> bool {
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !value.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+fn digits(value: &str, separators: &[char]) -> Option<Vec<u32>> {
+    value
+        .chars()
+        .filter(|c| !separators.contains(c))
+        .map(|c| c.to_digit(10))
+        .collect()
+}
+
+fn is_credit_card(value: &str) -> bool {
+    match digits(value, &[' ', '-']) {
+        Some(d) if (13..=19).contains(&d.len()) => {
+            // Luhn checksum
+            let sum: u32 = d
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(i, &n)| match i % 2 {
+                    1 if n * 2 > 9 => n * 2 - 9,
+                    1 => n * 2,
+                    _ => n,
+                })
+                .sum();
+            sum.is_multiple_of(10)
+        }
+        _ => false,
+    }
+}
+
+/// Phone numbers need a leading `+` or separators, so that bare numbers such as
+/// millisecond timestamps and order IDs aren't flagged.
+fn is_phone_number(value: &str) -> bool {
+    const SEPARATORS: &[char] = &[' ', '-', '(', ')', '.'];
+    let (formatted, value) = match value.strip_prefix('+') {
+        Some(value) => (true, value),
+        None => (
+            value.contains(SEPARATORS) && value.parse::<f64>().is_err(),
+            value,
+        ),
+    };
+    formatted && digits(value, SEPARATORS).is_some_and(|d| (10..=15).contains(&d.len()))
+}
+
+impl HoneyComb {
+    /// Flag columns that are likely to contain personal data, producing a review list.
+    /// Every column is checked by key name and the string columns are sampled with
+    /// group-by queries over the last `range_seconds` to check the shape of the values.
+    /// Sampling is skipped if the key lacks query access.
+    pub async fn find_pii_columns(
+        &self,
+        dataset_slug: &str,
+        columns: &[Column],
+        range_seconds: usize,
+    ) -> anyhow::Result<PiiReport> {
+        let string_columns = columns
+            .iter()
+            .filter(|c| c.r#type == ColumnType::String)
+            .map(|c| c.key_name.clone())
+            .collect::<Vec<_>>();
+        let auth = self.list_authorizations().await?;
+        let variants = ReportSection::run(
+            &auth,
+            &[QUERIES_ACCESS],
+            self.get_all_group_by_variants(dataset_slug, &string_columns, range_seconds),
+        )
+        .await?;
+        let no_variants = vec![];
+        let sampled = variants
+            .complete()
+            .map(|report| &report.succeeded)
+            .unwrap_or(&no_variants);
+
+        let mut findings = Vec::new();
+        for column in columns {
+            let mut reasons = Vec::new();
+            if let Some(segment) = pii_key_segment(&column.key_name) {
+                reasons.push(PiiReason::KeyName(segment.to_string()));
+            }
+            if let Some((_, values)) = sampled.iter().find(|(k, _)| *k == column.key_name) {
+                for kind in [
+                    PiiKind::Email,
+                    PiiKind::IpAddress,
+                    PiiKind::CreditCard,
+                    PiiKind::PhoneNumber,
+                ] {
+                    let matched = values
+                        .iter()
+                        .filter(|v| pii_value_kind(&v.value) == Some(kind))
+                        .count();
+                    if matched > 0 {
+                        reasons.push(PiiReason::ValueShape {
+                            kind,
+                            matched,
+                            sampled: values.len(),
+                        });
+                    }
+                }
+            }
+            if !reasons.is_empty() {
+                findings.push(PiiFinding {
+                    dataset: dataset_slug.to_string(),
+                    key_name: column.key_name.clone(),
+                    reasons,
+                });
+            }
+        }
+        Ok(PiiReport {
+            findings,
+            value_sampling: variants.map(|report| report.succeeded.len()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names_match_on_whole_segments() {
+        assert_eq!(pii_key_segment("user.email"), Some("email"));
+        assert_eq!(pii_key_segment("client.address"), Some("address"));
+        assert_eq!(pii_key_segment("app.user_phone"), Some("phone"));
+        assert_eq!(pii_key_segment("Net.Peer.IP"), Some("ip"));
+        assert_eq!(pii_key_segment("http.route"), None);
+        assert_eq!(pii_key_segment("app.zip_enabled"), None);
+        assert_eq!(pii_key_segment("app.description"), None);
+    }
+
+    #[test]
+    fn values_are_classified_by_shape() {
+        let cases = [
+            ("jane@example.com", Some(PiiKind::Email)),
+            ("10.0.0.1", Some(PiiKind::IpAddress)),
+            ("::1", Some(PiiKind::IpAddress)),
+            ("4111 1111 1111 1111", Some(PiiKind::CreditCard)),
+            ("+44 20 7946 0958", Some(PiiKind::PhoneNumber)),
+            ("(555) 010-4477", Some(PiiKind::PhoneNumber)),
+        ];
+        for (value, kind) in cases {
+            assert_eq!(pii_value_kind(value), kind, "{}", value);
+        }
+    }
+
+    #[test]
+    fn lookalikes_are_not_pii() {
+        for value in [
+            "@handle",
+            "jane@localhost",
+            "a@b@example.com",
+            // Fails the Luhn check
+            "4111 1111 1111 1112",
+            // Bare numbers such as timestamps and order IDs
+            "1700000000000",
+            "12.5",
+            "GET /checkout",
+        ] {
+            assert_eq!(pii_value_kind(value), None, "{}", value);
+        }
+    }
+}
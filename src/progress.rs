@@ -0,0 +1,78 @@
+use std::{collections::VecDeque, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::{honeycomb::HoneyComb, retry::RetrySnapshot};
+
+/// How many recent completions to use for the current rate.
+const WINDOW: usize = 10;
+
+/// Estimates the time remaining for a bulk operation. Early items often finish before
+/// rate limiting kicks in, so the estimate takes the slowest of the overall rate, the
+/// recent rate and the client's rate limiter, and reports throttling as it happens.
+pub(crate) struct Eta {
+    total: usize,
+    done: usize,
+    started: Instant,
+    recent: VecDeque<Instant>,
+    min_per_item: Duration,
+    retries_at_start: RetrySnapshot,
+}
+
+impl Eta {
+    pub(crate) fn new(hc: &HoneyComb, total: usize) -> Self {
+        Self {
+            total,
+            done: 0,
+            started: Instant::now(),
+            recent: VecDeque::with_capacity(WINDOW),
+            min_per_item: hc
+                .rate_limiter
+                .as_ref()
+                .map(|r| r.refill_every())
+                .unwrap_or_default(),
+            retries_at_start: hc.retry_stats(),
+        }
+    }
+
+    pub(crate) fn complete(&mut self) {
+        self.done += 1;
+        if self.recent.len() == WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(Instant::now());
+    }
+
+    pub(crate) fn remaining(&self) -> Option<Duration> {
+        if self.done == 0 {
+            return None;
+        }
+        let overall = self.started.elapsed() / self.done as u32;
+        let recent = match (self.recent.front(), self.recent.back()) {
+            (Some(first), Some(last)) if self.recent.len() > 1 => {
+                last.duration_since(*first) / (self.recent.len() - 1) as u32
+            }
+            _ => Duration::ZERO,
+        };
+        let per_item = overall.max(recent).max(self.min_per_item);
+        Some(per_item * self.total.saturating_sub(self.done) as u32)
+    }
+
+    pub(crate) fn message(&self, hc: &HoneyComb) -> String {
+        let retries = hc.retry_stats();
+        let throttled = retries.throttled - self.retries_at_start.throttled;
+        let mut message = String::from("Rate-limited queries, please wait...");
+        if let Some(remaining) = self.remaining() {
+            message.push_str(&format!(" ~{}s remaining", remaining.as_secs()));
+        }
+        if throttled > 0 {
+            let backoff = retries.backoff - self.retries_at_start.backoff;
+            message.push_str(&format!(
+                " (throttled {} times, {}s backing off)",
+                throttled,
+                backoff.as_secs()
+            ));
+        }
+        message
+    }
+}
@@ -0,0 +1,841 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::api_enum::api_enum;
+
+/// A query as accepted by the Queries API.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuerySpec {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakdowns: Vec<Breakdown>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub calculations: Vec<Calculation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<Filter>,
+    /// How `filters` are combined, `AND` when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter_combination: Option<FilterCombination>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub orders: Vec<Order>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub havings: Vec<Having>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Seconds before now, or before/after `end_time`/`start_time` when one is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_range: Option<usize>,
+    #[serde(
+        default,
+        with = "chrono::serde::ts_seconds_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_time: Option<DateTime<Utc>>,
+    #[serde(
+        default,
+        with = "chrono::serde::ts_seconds_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub end_time: Option<DateTime<Utc>>,
+    /// Seconds per bucket of the time series, chosen by the API when not set. The API
+    /// accepts between a thousandth and a tenth of the time range.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub granularity: Option<usize>,
+}
+
+/// A column to group results by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Breakdown(pub String);
+
+impl From<&str> for Breakdown {
+    fn from(column: &str) -> Self {
+        Self(column.to_string())
+    }
+}
+
+impl From<String> for Breakdown {
+    fn from(column: String) -> Self {
+        Self(column)
+    }
+}
+
+api_enum! {
+    /// A calculation operation supported by the Queries API.
+    pub enum CalcOp {
+        Count => "COUNT",
+        Concurrency => "CONCURRENCY",
+        CountDistinct => "COUNT_DISTINCT",
+        Sum => "SUM",
+        Avg => "AVG",
+        Max => "MAX",
+        Min => "MIN",
+        Heatmap => "HEATMAP",
+        P001 => "P001",
+        P01 => "P01",
+        P05 => "P05",
+        P10 => "P10",
+        P20 => "P20",
+        P25 => "P25",
+        P50 => "P50",
+        P75 => "P75",
+        P80 => "P80",
+        P90 => "P90",
+        P95 => "P95",
+        P99 => "P99",
+        P999 => "P999",
+        RateAvg => "RATE_AVG",
+        RateSum => "RATE_SUM",
+        RateMax => "RATE_MAX",
+    }
+}
+
+impl CalcOp {
+    /// `COUNT` and `CONCURRENCY` count events; every other op is applied to a column.
+    pub fn needs_column(&self) -> bool {
+        !matches!(self, Self::Count | Self::Concurrency)
+    }
+}
+
+/// The percentiles most used for latency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Percentile {
+    P50,
+    P75,
+    P90,
+    P95,
+    P99,
+    P999,
+}
+
+impl From<Percentile> for CalcOp {
+    fn from(percentile: Percentile) -> Self {
+        match percentile {
+            Percentile::P50 => Self::P50,
+            Percentile::P75 => Self::P75,
+            Percentile::P90 => Self::P90,
+            Percentile::P95 => Self::P95,
+            Percentile::P99 => Self::P99,
+            Percentile::P999 => Self::P999,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calculation {
+    pub op: CalcOp,
+    /// Not used by `COUNT` and `CONCURRENCY`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+}
+
+/// Constructors for calculations that take a column, e.g. `Calculation::p99("duration_ms")`.
+macro_rules! column_calculations {
+    ($($name:ident => $op:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("`", stringify!($op), "` of `column`.")]
+            pub fn $name(column: &str) -> Self {
+                Self::new(CalcOp::$op, Some(column))
+            }
+        )*
+    };
+}
+
+impl Calculation {
+    pub fn new(op: impl Into<CalcOp>, column: Option<&str>) -> Self {
+        Self {
+            op: op.into(),
+            column: column.map(str::to_string),
+        }
+    }
+
+    pub fn count() -> Self {
+        Self::new(CalcOp::Count, None)
+    }
+
+    pub fn concurrency() -> Self {
+        Self::new(CalcOp::Concurrency, None)
+    }
+
+    column_calculations! {
+        count_distinct => CountDistinct,
+        sum => Sum,
+        avg => Avg,
+        max => Max,
+        min => Min,
+        heatmap => Heatmap,
+        p001 => P001,
+        p01 => P01,
+        p05 => P05,
+        p10 => P10,
+        p20 => P20,
+        p25 => P25,
+        p50 => P50,
+        p75 => P75,
+        p80 => P80,
+        p90 => P90,
+        p95 => P95,
+        p99 => P99,
+        p999 => P999,
+        rate_avg => RateAvg,
+        rate_sum => RateSum,
+        rate_max => RateMax,
+    }
+}
+
+api_enum! {
+    pub enum FilterOp {
+        Exists => "exists",
+        DoesNotExist => "does-not-exist",
+        Eq => "=",
+        Ne => "!=",
+        Gt => ">",
+        Ge => ">=",
+        Lt => "<",
+        Le => "<=",
+        StartsWith => "starts-with",
+        DoesNotStartWith => "does-not-start-with",
+        Contains => "contains",
+        DoesNotContain => "does-not-contain",
+        In => "in",
+        NotIn => "not-in",
+    }
+}
+
+impl FilterOp {
+    /// `exists` and `does-not-exist` are the only ops without a value.
+    pub fn takes_value(&self) -> bool {
+        !matches!(self, Self::Exists | Self::DoesNotExist)
+    }
+}
+
+/// A filter value. `in` and `not-in` take a list, every other op a single value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FilterValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<FilterValue>),
+}
+
+impl From<bool> for FilterValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<&str> for FilterValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for FilterValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+/// Numbers, the only values the ordering ops accept.
+pub trait Numeric: Into<FilterValue> {}
+
+macro_rules! numeric {
+    ($variant:ident as $as:ty: $($ty:ty),*) => {
+        $(
+            impl From<$ty> for FilterValue {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(value as $as)
+                }
+            }
+
+            impl Numeric for $ty {}
+        )*
+    };
+}
+
+numeric!(Integer as i64: i32, i64, u16, u32);
+numeric!(Float as f64: f32, f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum FilterCombination {
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Filter {
+    pub column: String,
+    pub op: FilterOp,
+    /// Not used by `exists` and `does-not-exist`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<FilterValue>,
+}
+
+impl Filter {
+    /// Any op and value, unchecked. Prefer the constructors for each op, which only
+    /// accept the values the API does.
+    pub fn new(column: &str, op: impl Into<FilterOp>, value: Option<FilterValue>) -> Self {
+        Self {
+            column: column.to_string(),
+            op: op.into(),
+            value,
+        }
+    }
+
+    pub fn exists(column: &str) -> Self {
+        Self::new(column, FilterOp::Exists, None)
+    }
+
+    pub fn does_not_exist(column: &str) -> Self {
+        Self::new(column, FilterOp::DoesNotExist, None)
+    }
+
+    pub fn eq(column: &str, value: impl Into<FilterValue>) -> Self {
+        Self::new(column, FilterOp::Eq, Some(value.into()))
+    }
+
+    pub fn ne(column: &str, value: impl Into<FilterValue>) -> Self {
+        Self::new(column, FilterOp::Ne, Some(value.into()))
+    }
+
+    pub fn gt(column: &str, value: impl Numeric) -> Self {
+        Self::new(column, FilterOp::Gt, Some(value.into()))
+    }
+
+    pub fn ge(column: &str, value: impl Numeric) -> Self {
+        Self::new(column, FilterOp::Ge, Some(value.into()))
+    }
+
+    pub fn lt(column: &str, value: impl Numeric) -> Self {
+        Self::new(column, FilterOp::Lt, Some(value.into()))
+    }
+
+    pub fn le(column: &str, value: impl Numeric) -> Self {
+        Self::new(column, FilterOp::Le, Some(value.into()))
+    }
+
+    pub fn contains(column: &str, value: &str) -> Self {
+        Self::new(column, FilterOp::Contains, Some(value.into()))
+    }
+
+    pub fn does_not_contain(column: &str, value: &str) -> Self {
+        Self::new(column, FilterOp::DoesNotContain, Some(value.into()))
+    }
+
+    pub fn starts_with(column: &str, value: &str) -> Self {
+        Self::new(column, FilterOp::StartsWith, Some(value.into()))
+    }
+
+    pub fn does_not_start_with(column: &str, value: &str) -> Self {
+        Self::new(column, FilterOp::DoesNotStartWith, Some(value.into()))
+    }
+
+    pub fn is_in<V: Into<FilterValue>>(column: &str, values: impl IntoIterator<Item = V>) -> Self {
+        let values = values.into_iter().map(Into::into).collect();
+        Self::new(column, FilterOp::In, Some(FilterValue::List(values)))
+    }
+
+    pub fn not_in<V: Into<FilterValue>>(column: &str, values: impl IntoIterator<Item = V>) -> Self {
+        let values = values.into_iter().map(Into::into).collect();
+        Self::new(column, FilterOp::NotIn, Some(FilterValue::List(values)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// Order by a breakdown `column`, or by a calculation `op` (with its `column` if it has one).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub op: Option<CalcOp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<SortOrder>,
+}
+
+impl Order {
+    /// Order by a breakdown column.
+    pub fn by_column(column: &str, order: SortOrder) -> Self {
+        Self {
+            column: Some(column.to_string()),
+            op: None,
+            order: Some(order),
+        }
+    }
+
+    /// Order by a calculation, which must also be one of the query's calculations.
+    pub fn by_calc(calculation: Calculation, order: SortOrder) -> Self {
+        Self {
+            column: calculation.column,
+            op: Some(calculation.op),
+            order: Some(order),
+        }
+    }
+}
+
+/// Filter result groups on the value of a calculation, e.g. only endpoints with
+/// `COUNT > 100`. The calculation must also be one of the query's calculations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Having {
+    pub calculate_op: CalcOp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    /// One of the comparison ops, `=`, `!=`, `>`, `>=`, `<` or `<=`.
+    pub op: FilterOp,
+    pub value: FilterValue,
+}
+
+impl Having {
+    fn new(calculation: Calculation, op: FilterOp, value: impl Numeric) -> Self {
+        Self {
+            calculate_op: calculation.op,
+            column: calculation.column,
+            op,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq(calculation: Calculation, value: impl Numeric) -> Self {
+        Self::new(calculation, FilterOp::Eq, value)
+    }
+
+    pub fn ne(calculation: Calculation, value: impl Numeric) -> Self {
+        Self::new(calculation, FilterOp::Ne, value)
+    }
+
+    pub fn gt(calculation: Calculation, value: impl Numeric) -> Self {
+        Self::new(calculation, FilterOp::Gt, value)
+    }
+
+    pub fn ge(calculation: Calculation, value: impl Numeric) -> Self {
+        Self::new(calculation, FilterOp::Ge, value)
+    }
+
+    pub fn lt(calculation: Calculation, value: impl Numeric) -> Self {
+        Self::new(calculation, FilterOp::Lt, value)
+    }
+
+    pub fn le(calculation: Calculation, value: impl Numeric) -> Self {
+        Self::new(calculation, FilterOp::Le, value)
+    }
+
+    pub fn calculation(&self) -> Calculation {
+        Calculation {
+            op: self.calculate_op.clone(),
+            column: self.column.clone(),
+        }
+    }
+}
+
+/// A query saved in Honeycomb, identified by its ID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub id: String,
+    #[serde(flatten)]
+    pub spec: QuerySpec,
+}
+
+impl QuerySpec {
+    pub fn builder() -> QueryBuilder {
+        QueryBuilder::default()
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    /// See [`canonical_query`].
+    pub fn canonical(&self) -> String {
+        canonical_query(&self.to_value())
+    }
+
+    /// See [`query_hash`].
+    pub fn spec_hash(&self) -> u64 {
+        query_hash(&self.to_value())
+    }
+
+    /// The length of the queried window in seconds, if the spec sets one. Without one the
+    /// API queries the last two hours.
+    pub fn range_seconds(&self) -> Option<usize> {
+        match (self.start_time, self.end_time) {
+            (Some(start), Some(end)) => Some((end - start).num_seconds().max(0) as usize),
+            _ => self.time_range,
+        }
+    }
+}
+
+/// The window a query covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeRange {
+    LastHour,
+    Last24Hours,
+    /// Seven days less a second, which stays inside the seven day query limit.
+    Last7Days,
+    /// A window of this length ending now.
+    Custom(Duration),
+    Absolute {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+}
+
+impl TimeRange {
+    /// The length of the window in seconds.
+    pub fn seconds(&self) -> usize {
+        match self {
+            Self::LastHour => 3600,
+            Self::Last24Hours => 86400,
+            Self::Last7Days => 604799,
+            Self::Custom(duration) => duration.as_secs() as usize,
+            Self::Absolute { start, end } => (*end - *start).num_seconds().max(0) as usize,
+        }
+    }
+}
+
+/// Build a [`QuerySpec`] fluently:
+/// `QuerySpec::builder().calc(Calculation::p99("duration_ms")).breakdown("service.name").last_days(7).build()`
+#[derive(Debug, Clone, Default)]
+pub struct QueryBuilder {
+    spec: QuerySpec,
+}
+
+impl QueryBuilder {
+    pub fn calc(mut self, calculation: Calculation) -> Self {
+        self.spec.calculations.push(calculation);
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.spec.filters.push(filter);
+        self
+    }
+
+    pub fn filters(mut self, filters: impl IntoIterator<Item = Filter>) -> Self {
+        self.spec.filters.extend(filters);
+        self
+    }
+
+    /// Match events passing any filter rather than all of them.
+    pub fn any_filter(mut self) -> Self {
+        self.spec.filter_combination = Some(FilterCombination::Or);
+        self
+    }
+
+    pub fn filter_combination(mut self, combination: FilterCombination) -> Self {
+        self.spec.filter_combination = Some(combination);
+        self
+    }
+
+    /// Adds the having's calculation to the query too if it isn't there already.
+    pub fn having(mut self, having: Having) -> Self {
+        let calculation = having.calculation();
+        if !self.spec.calculations.contains(&calculation) {
+            self.spec.calculations.push(calculation);
+        }
+        self.spec.havings.push(having);
+        self
+    }
+
+    pub fn order(mut self, order: Order) -> Self {
+        self.spec.orders.push(order);
+        self
+    }
+
+    /// Order by a calculation, largest first, adding it to the query if it isn't there
+    /// already. With a limit this gives the top N groups.
+    pub fn top_by(mut self, calculation: Calculation) -> Self {
+        if !self.spec.calculations.contains(&calculation) {
+            self.spec.calculations.push(calculation.clone());
+        }
+        self.order(Order::by_calc(calculation, SortOrder::Descending))
+    }
+
+    pub fn breakdown(mut self, column: impl Into<Breakdown>) -> Self {
+        self.spec.breakdowns.push(column.into());
+        self
+    }
+
+    /// Group by dataset, for queries across [`ALL_DATASETS`](crate::honeycomb::ALL_DATASETS).
+    pub fn by_dataset(self) -> Self {
+        self.breakdown(DATASET_COLUMN)
+    }
+
+    pub fn breakdowns<B: Into<Breakdown>>(mut self, columns: impl IntoIterator<Item = B>) -> Self {
+        self.spec
+            .breakdowns
+            .extend(columns.into_iter().map(Into::into));
+        self
+    }
+
+    /// The most groups to return, with [`QueryBuilder::top_by`] the N in top N.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.spec.limit = Some(limit);
+        self
+    }
+
+    /// Seconds before now.
+    pub fn time_range(mut self, seconds: usize) -> Self {
+        self.spec.time_range = Some(seconds);
+        self
+    }
+
+    pub fn range(self, range: TimeRange) -> Self {
+        match range {
+            TimeRange::Absolute { start, end } => self.between(start, end),
+            relative => self.time_range(relative.seconds()),
+        }
+    }
+
+    /// An absolute window, e.g. an incident last month.
+    pub fn between(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.spec.time_range = None;
+        self.spec.start_time = Some(start);
+        self.spec.end_time = Some(end);
+        self
+    }
+
+    /// With [`QueryBuilder::time_range`], the window starting at `start`.
+    pub fn start_time(mut self, start: DateTime<Utc>) -> Self {
+        self.spec.start_time = Some(start);
+        self
+    }
+
+    /// With [`QueryBuilder::time_range`], the window ending at `end`.
+    pub fn end_time(mut self, end: DateTime<Utc>) -> Self {
+        self.spec.end_time = Some(end);
+        self
+    }
+
+    /// Seconds per bucket of the time series.
+    pub fn granularity(mut self, seconds: usize) -> Self {
+        self.spec.granularity = Some(seconds);
+        self
+    }
+
+    pub fn last_hours(self, hours: usize) -> Self {
+        self.time_range(hours * 3600)
+    }
+
+    pub fn last_days(self, days: usize) -> Self {
+        self.time_range(days * 86400)
+    }
+
+    pub fn build(self) -> QuerySpec {
+        self.spec
+    }
+}
+
+/// A query result with its rows deserialized into `T`, e.g. a struct with a field per
+/// breakdown and calculation (`#[serde(rename = "COUNT")]`), or the default map.
+/// Serializes back to the API's shape, so a stored result parses like a fresh one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    from = "RawQueryResult<T>",
+    into = "RawQueryResult<T>",
+    bound(serialize = "T: Serialize + Clone")
+)]
+pub struct QueryResultData<T = Map<String, Value>> {
+    pub id: Option<String>,
+    /// False while the query is still running, in which case there are no rows yet.
+    pub complete: bool,
+    pub query: Option<QuerySpec>,
+    pub results: Vec<T>,
+    pub series: Vec<SeriesPoint<T>>,
+    pub query_url: Option<String>,
+    pub graph_image_url: Option<String>,
+}
+
+/// The breakdown column naming the dataset of each group when querying
+/// [`ALL_DATASETS`](crate::honeycomb::ALL_DATASETS).
+pub const DATASET_COLUMN: &str = "dataset";
+
+impl QueryResultData {
+    /// Split the rows of an environment-wide query broken down by [`DATASET_COLUMN`] by
+    /// dataset, removing the column from each row.
+    pub fn by_dataset(self) -> BTreeMap<String, Vec<Map<String, Value>>> {
+        let mut datasets = BTreeMap::<_, Vec<_>>::new();
+        for mut row in self.results {
+            let dataset = match row.remove(DATASET_COLUMN) {
+                Some(Value::String(dataset)) => dataset,
+                _ => String::new(),
+            };
+            datasets.entry(dataset).or_default().push(row);
+        }
+        datasets
+    }
+}
+
+/// A started query result, enough to poll for its rows or to link to it in the UI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryHandle {
+    pub dataset: String,
+    pub query_id: String,
+    pub result_id: String,
+    /// The permalink to the result in the Honeycomb UI.
+    pub url: String,
+}
+
+impl Display for QueryHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.url)
+    }
+}
+
+/// How often to check whether a query result is complete, and for how long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollPolicy {
+    pub interval: Duration,
+    pub deadline: Duration,
+}
+
+impl Default for PollPolicy {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(100),
+            deadline: Duration::from_secs(5),
+        }
+    }
+}
+
+/// The rows of a query run by [`HoneyComb::run_query`](crate::honeycomb::HoneyComb::run_query).
+pub type QueryOutput<T = Map<String, Value>> = QueryResultData<T>;
+
+/// One bucket of the time series.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeriesPoint<T> {
+    pub time: DateTime<Utc>,
+    pub data: T,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RawQueryResult<T> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    complete: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    query: Option<QuerySpec>,
+    // Explicit defaults so serde doesn't require `T: Default`
+    #[serde(default = "Option::default", skip_serializing_if = "Option::is_none")]
+    data: Option<RawResultData<T>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    links: Option<RawResultLinks>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RawResultData<T> {
+    #[serde(default = "Vec::new")]
+    series: Vec<SeriesPoint<T>>,
+    #[serde(default = "Vec::new")]
+    results: Vec<RawResultRow<T>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RawResultRow<T> {
+    data: T,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RawResultLinks {
+    query_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    graph_image_url: Option<String>,
+}
+
+impl<T> From<RawQueryResult<T>> for QueryResultData<T> {
+    fn from(raw: RawQueryResult<T>) -> Self {
+        let (series, results) = match raw.data {
+            Some(data) => (
+                data.series,
+                data.results.into_iter().map(|r| r.data).collect(),
+            ),
+            None => (vec![], vec![]),
+        };
+        Self {
+            id: raw.id,
+            complete: raw.complete,
+            query: raw.query,
+            results,
+            series,
+            query_url: raw.links.as_ref().map(|l| l.query_url.clone()),
+            graph_image_url: raw.links.and_then(|l| l.graph_image_url),
+        }
+    }
+}
+
+impl<T> From<QueryResultData<T>> for RawQueryResult<T> {
+    fn from(data: QueryResultData<T>) -> Self {
+        Self {
+            id: data.id,
+            complete: data.complete,
+            query: data.query,
+            data: Some(RawResultData {
+                series: data.series,
+                results: data
+                    .results
+                    .into_iter()
+                    .map(|data| RawResultRow { data })
+                    .collect(),
+            }),
+            links: data.query_url.map(|query_url| RawResultLinks {
+                query_url,
+                graph_image_url: data.graph_image_url,
+            }),
+        }
+    }
+}
+
+/// Keys whose array order doesn't change the meaning of a query.
+const UNORDERED_KEYS: &[&str] = &["filters", "havings"];
+
+fn canonicalize(value: &Value, key: Option<&str>) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), canonicalize(v, Some(k))))
+                .collect(),
+        ),
+        Value::Array(items) => {
+            let mut items = items
+                .iter()
+                .map(|v| canonicalize(v, None))
+                .collect::<Vec<_>>();
+            if key.is_some_and(|k| UNORDERED_KEYS.contains(&k)) {
+                items.sort_by_cached_key(|v| v.to_string());
+            }
+            Value::Array(items)
+        }
+        _ => value.clone(),
+    }
+}
+
+/// Serialize a query so that equivalent queries produce identical strings: object keys
+/// are sorted, null fields are dropped, and filters and havings are put in a fixed order.
+pub fn canonical_query(query: &Value) -> String {
+    canonicalize(query, None).to_string()
+}
+
+/// A hash of the canonical query that is stable across runs, platforms and crate
+/// versions (64-bit FNV-1a), suitable for cache keys and change detection.
+pub fn query_hash(query: &Value) -> u64 {
+    canonical_query(query)
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
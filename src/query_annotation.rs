@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::honeycomb::HoneyComb;
+
+/// A name and description attached to a query, shown with it in the Honeycomb UI.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryAnnotation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub query_id: String,
+    /// `query` or `board`, set by the API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl QueryAnnotation {
+    pub fn new(query_id: &str, name: &str, description: Option<&str>) -> Self {
+        Self {
+            id: None,
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            query_id: query_id.to_string(),
+            source: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+}
+
+impl HoneyComb {
+    pub async fn list_query_annotations(
+        &self,
+        dataset_slug: &str,
+    ) -> anyhow::Result<Vec<QueryAnnotation>> {
+        self.get(&format!("query_annotations/{}", dataset_slug))
+            .await
+    }
+
+    pub async fn get_query_annotation(
+        &self,
+        dataset_slug: &str,
+        annotation_id: &str,
+    ) -> anyhow::Result<QueryAnnotation> {
+        self.get(&format!(
+            "query_annotations/{}/{}",
+            dataset_slug, annotation_id
+        ))
+        .await
+    }
+
+    pub async fn create_query_annotation(
+        &self,
+        dataset_slug: &str,
+        annotation: &QueryAnnotation,
+    ) -> anyhow::Result<QueryAnnotation> {
+        self.post(
+            &format!("query_annotations/{}", dataset_slug),
+            serde_json::to_value(annotation)?,
+        )
+        .await
+    }
+
+    pub async fn update_query_annotation(
+        &self,
+        dataset_slug: &str,
+        annotation_id: &str,
+        annotation: &QueryAnnotation,
+    ) -> anyhow::Result<QueryAnnotation> {
+        self.put(
+            &format!("query_annotations/{}/{}", dataset_slug, annotation_id),
+            serde_json::to_value(annotation)?,
+        )
+        .await
+    }
+
+    /// Fails with a not-found [`crate::error::StatusError`] if there is no such annotation.
+    pub async fn delete_query_annotation(
+        &self,
+        dataset_slug: &str,
+        annotation_id: &str,
+    ) -> anyhow::Result<()> {
+        self.delete(&format!(
+            "query_annotations/{}/{}",
+            dataset_slug, annotation_id
+        ))
+        .await
+    }
+}
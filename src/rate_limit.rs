@@ -0,0 +1,104 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{sync::Mutex, time::Instant};
+
+/// A token bucket shared by every clone of the client, so concurrent tasks draw from
+/// the same budget and stay under the API limits rather than waiting for 429s.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_every: Duration,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Allow bursts of up to `capacity` requests, refilling one token every `refill_every`.
+    /// A `capacity` of 0 is treated as 1, since a request always needs a whole token.
+    pub fn new(capacity: u32, refill_every: Duration) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity: capacity as f64,
+            refill_every,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: capacity as f64,
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+
+    /// `requests` per `period`, starting with a full bucket.
+    pub fn per(requests: u32, period: Duration) -> Self {
+        Self::new(requests, period / requests.max(1))
+    }
+
+    /// Honeycomb's documented Query Data API limit of 10 query results per minute.
+    pub fn query_data_api() -> Self {
+        Self::per(10, Duration::from_secs(60))
+    }
+
+    /// The sustained interval between requests once the burst is used up.
+    pub fn refill_every(&self) -> Duration {
+        self.refill_every
+    }
+
+    /// Wait until a token is available and take it.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+                let refilled = now.duration_since(bucket.last_refill).as_secs_f64()
+                    / self.refill_every.as_secs_f64();
+                bucket.tokens = (bucket.tokens + refilled).min(self.capacity);
+                bucket.last_refill = now;
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                self.refill_every.mul_f64(1.0 - bucket.tokens)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Acquire a token, returning how long that took on the paused clock.
+    async fn acquire_time(limiter: &RateLimiter) -> Duration {
+        let started = Instant::now();
+        limiter.acquire().await;
+        started.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_burst_is_followed_by_the_refill_rate() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(1));
+        assert_eq!(acquire_time(&limiter).await, Duration::ZERO);
+        assert_eq!(acquire_time(&limiter).await, Duration::ZERO);
+        assert_eq!(acquire_time(&limiter).await, Duration::from_secs(1));
+        assert_eq!(acquire_time(&limiter).await, Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn an_idle_bucket_refills_up_to_capacity() {
+        let limiter = RateLimiter::per(10, Duration::from_secs(60));
+        assert_eq!(limiter.refill_every(), Duration::from_secs(6));
+        for _ in 0..10 {
+            limiter.acquire().await;
+        }
+        tokio::time::advance(Duration::from_secs(600)).await;
+        for _ in 0..10 {
+            assert_eq!(acquire_time(&limiter).await, Duration::ZERO);
+        }
+        assert_eq!(acquire_time(&limiter).await, Duration::from_secs(6));
+    }
+}
@@ -0,0 +1,97 @@
+use std::future::Future;
+
+use serde::Serialize;
+
+use crate::{circuit_breaker::CircuitOpen, error::Cancelled, honeycomb::Authorizations};
+
+/// A section of a report that depends on particular API access. When the key lacks
+/// that access the section is skipped with a reason instead of failing the whole run.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportSection<T> {
+    Complete(T),
+    Skipped(String),
+}
+
+impl<T> ReportSection<T> {
+    /// Run `section` if `auth` grants all of `required_access`, otherwise skip it.
+    pub async fn run<F>(
+        auth: &Authorizations,
+        required_access: &[&str],
+        section: F,
+    ) -> anyhow::Result<Self>
+    where
+        F: Future<Output = anyhow::Result<T>>,
+    {
+        let missing = auth.missing_access(required_access);
+        if missing.is_empty() {
+            Ok(Self::Complete(section.await?))
+        } else {
+            Ok(Self::Skipped(format!(
+                "missing access: {}",
+                missing.join(", ")
+            )))
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> ReportSection<U> {
+        match self {
+            Self::Complete(t) => ReportSection::Complete(f(t)),
+            Self::Skipped(reason) => ReportSection::Skipped(reason),
+        }
+    }
+
+    pub fn complete(&self) -> Option<&T> {
+        match self {
+            Self::Complete(t) => Some(t),
+            Self::Skipped(_) => None,
+        }
+    }
+}
+
+/// The outcome of a bulk operation, so automation can retry only what didn't succeed.
+#[derive(Debug)]
+pub struct BulkReport<T> {
+    pub succeeded: Vec<T>,
+    /// Items that failed, with the error.
+    pub failed: Vec<(String, anyhow::Error)>,
+    /// Items that were not attempted, with the reason.
+    pub skipped: Vec<(String, String)>,
+}
+
+impl<T> Default for BulkReport<T> {
+    fn default() -> Self {
+        Self {
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+}
+
+impl<T> BulkReport<T> {
+    /// Record the result for an item. Requests refused by an open circuit or cancelled
+    /// didn't get an answer from the API so they count as skipped rather than failed.
+    pub(crate) fn record(&mut self, item: String, result: anyhow::Result<T>) {
+        match result {
+            Ok(t) => self.succeeded.push(t),
+            Err(e) if e.is::<CircuitOpen>() || e.is::<Cancelled>() => {
+                self.skipped.push((item, e.to_string()))
+            }
+            Err(e) => self.failed.push((item, e)),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.skipped.is_empty()
+    }
+
+    /// The failed and skipped items.
+    pub fn retry_items(&self) -> Vec<String> {
+        self.failed
+            .iter()
+            .map(|(item, _)| item.clone())
+            .chain(self.skipped.iter().map(|(item, _)| item.clone()))
+            .collect()
+    }
+}
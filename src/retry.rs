@@ -0,0 +1,176 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Controls how requests are retried when the API is rate limiting or failing transiently.
+///
+/// The delay doubles with each attempt starting from `base_delay`, capped at `max_delay`.
+/// A `Retry-After` header replaces the delay but is capped at `max_delay` too.
+/// With `jitter` enabled a random delay between half and all of that value is used so
+/// that concurrent tasks don't retry in lockstep.
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryPolicy {
+    /// Attempts including the first; 0 is treated as 1.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 12,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The number of attempts to make, at least one.
+    pub fn attempts(&self) -> u32 {
+        self.max_attempts.max(1)
+    }
+
+    /// The backoff delay before retrying after the given (zero-based) attempt.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        if self.jitter {
+            let half = delay / 2;
+            half + half.mul_f64(random_fraction())
+        } else {
+            delay
+        }
+    }
+}
+
+/// Retry totals shared by a client and its clones.
+#[derive(Debug, Default)]
+pub(crate) struct RetryStats {
+    retries: AtomicU64,
+    throttled: AtomicU64,
+    backoff_ms: AtomicU64,
+}
+
+impl RetryStats {
+    pub(crate) fn record(&self, delay: Duration, throttled: bool) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+        if throttled {
+            self.throttled.fetch_add(1, Ordering::Relaxed);
+        }
+        self.backoff_ms
+            .fetch_add(delay.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> RetrySnapshot {
+        RetrySnapshot {
+            retries: self.retries.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+            backoff: Duration::from_millis(self.backoff_ms.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RetrySnapshot {
+    /// Requests that were retried for any reason.
+    pub retries: u64,
+    /// Retries caused by `429 Too Many Requests`.
+    pub throttled: u64,
+    /// Total time spent sleeping before retries.
+    pub backoff: Duration,
+}
+
+/// A random value in `[0, 1)` without pulling in an RNG dependency.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Rate limiting and transient server errors are worth retrying.
+pub(crate) fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(
+        status,
+        reqwest::StatusCode::TOO_MANY_REQUESTS
+            | reqwest::StatusCode::INTERNAL_SERVER_ERROR
+            | reqwest::StatusCode::BAD_GATEWAY
+            | reqwest::StatusCode::SERVICE_UNAVAILABLE
+            | reqwest::StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Connection failures, resets and timeouts are worth retrying.
+pub(crate) fn is_transient(e: &reqwest::Error) -> bool {
+    e.is_connect() || e.is_timeout() || e.is_request() || e.is_body()
+}
+
+/// Parse a `Retry-After` header given either as delay seconds or as an HTTP date.
+pub(crate) fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            jitter: false,
+        };
+        let delays = (0..5).map(|a| policy.delay(a)).collect::<Vec<_>>();
+        assert_eq!(delays, [1, 2, 4, 5, 5].map(Duration::from_secs));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn jitter_stays_between_half_and_all_of_the_delay() {
+        let policy = RetryPolicy::default();
+        for attempt in 0..10 {
+            let delay = policy.delay(attempt);
+            let full = RetryPolicy {
+                jitter: false,
+                ..policy.clone()
+            }
+            .delay(attempt);
+            assert!(full / 2 <= delay && delay <= full, "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn zero_attempts_still_sends_once() {
+        let policy = RetryPolicy {
+            max_attempts: 0,
+            ..Default::default()
+        };
+        assert_eq!(policy.attempts(), 1);
+    }
+}
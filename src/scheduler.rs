@@ -0,0 +1,342 @@
+use std::{str::FromStr, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Datelike, DurationRound, Timelike, Utc};
+use tokio::{sync::mpsc, task::JoinSet};
+
+use crate::{
+    honeycomb::HoneyComb,
+    query::{QueryOutput, QuerySpec},
+};
+
+/// How far ahead to look for the next match of a cron expression.
+const CRON_HORIZON_DAYS: i64 = 366 * 4;
+
+/// One field of a cron expression, as the set of values it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CronField(Vec<u32>);
+
+impl CronField {
+    fn parse(field: &str, min: u32, max: u32) -> anyhow::Result<Self> {
+        let mut values = Vec::new();
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>()?),
+                None => (part, 1),
+            };
+            anyhow::ensure!(step > 0, "step in {} must be positive", part);
+            let (start, end) = match range {
+                "*" => (min, max),
+                range => match range.split_once('-') {
+                    Some((start, end)) => (start.parse()?, end.parse()?),
+                    None => {
+                        let value = range.parse()?;
+                        (value, if part.contains('/') { max } else { value })
+                    }
+                },
+            };
+            anyhow::ensure!(
+                min <= start && start <= end && end <= max,
+                "{} is outside {}-{}",
+                part,
+                min,
+                max
+            );
+            values.extend((start..=end).step_by(step as usize));
+        }
+        values.sort_unstable();
+        values.dedup();
+        Ok(Self(values))
+    }
+
+    fn matches(&self, value: u32) -> bool {
+        self.0.binary_search(&value).is_ok()
+    }
+}
+
+/// A five field cron expression, `minute hour day-of-month month day-of-week`, evaluated
+/// in UTC. Fields accept `*`, numbers, ranges `a-b`, lists `a,b` and steps `*/n`; Sunday
+/// is 0. As in cron, when both day fields are restricted a day matching either runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: CronField,
+    hours: CronField,
+    days_of_month: CronField,
+    months: CronField,
+    days_of_week: CronField,
+    /// The field starts with `*`, e.g. `*/2`. As in cron, a day must match both day
+    /// fields when either starts with `*`, and either field when neither does.
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl FromStr for Cron {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> anyhow::Result<Self> {
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            anyhow::bail!("cron expression {:?} must have five fields", expression);
+        };
+        let parse = |field, min, max| {
+            CronField::parse(field, min, max)
+                .with_context(|| format!("bad cron expression {:?}", expression))
+        };
+        Ok(Self {
+            minutes: parse(minutes, 0, 59)?,
+            hours: parse(hours, 0, 23)?,
+            days_of_month: parse(days_of_month, 1, 31)?,
+            months: parse(months, 1, 12)?,
+            days_of_week: parse(days_of_week, 0, 6)?,
+            any_day_of_month: days_of_month.starts_with('*'),
+            any_day_of_week: days_of_week.starts_with('*'),
+        })
+    }
+}
+
+impl Cron {
+    fn matches_day(&self, time: DateTime<Utc>) -> bool {
+        let day_of_month = self.days_of_month.matches(time.day());
+        let day_of_week = self
+            .days_of_week
+            .matches(time.weekday().num_days_from_sunday());
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+
+    /// The first matching minute strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let minute = chrono::Duration::minutes(1);
+        let mut time = after.duration_trunc(minute).ok()? + minute;
+        let horizon = after + chrono::Duration::days(CRON_HORIZON_DAYS);
+        while time <= horizon {
+            if !self.months.matches(time.month()) || !self.matches_day(time) {
+                time = (time + chrono::Duration::days(1))
+                    .duration_trunc(chrono::Duration::days(1))
+                    .ok()?;
+            } else if !self.hours.matches(time.hour()) {
+                time = (time + chrono::Duration::hours(1))
+                    .duration_trunc(chrono::Duration::hours(1))
+                    .ok()?;
+            } else if !self.minutes.matches(time.minute()) {
+                time += minute;
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+}
+
+/// When a scheduled query runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// At a fixed interval, starting one interval after the scheduler starts.
+    Every(Duration),
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// Parse a five field cron expression, e.g. `*/15 * * * *`.
+    pub fn cron(expression: &str) -> anyhow::Result<Self> {
+        Ok(Self::Cron(expression.parse()?))
+    }
+
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Every(interval) => Some(
+                after
+                    + chrono::Duration::from_std(*interval)
+                        .ok()?
+                        .max(chrono::Duration::seconds(1)),
+            ),
+            Self::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+/// A named query to run on a schedule.
+#[derive(Debug, Clone)]
+pub struct ScheduledQuery {
+    pub name: String,
+    pub dataset: String,
+    pub spec: QuerySpec,
+    pub schedule: Schedule,
+}
+
+impl ScheduledQuery {
+    pub fn new(
+        name: impl Into<String>,
+        dataset: impl Into<String>,
+        spec: QuerySpec,
+        schedule: Schedule,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            dataset: dataset.into(),
+            spec,
+            schedule,
+        }
+    }
+}
+
+/// The outcome of one scheduled run. A failed run doesn't stop the schedule.
+#[derive(Debug)]
+pub struct ScheduledRun {
+    pub name: String,
+    pub dataset: String,
+    /// When the run was due.
+    pub due: DateTime<Utc>,
+    pub output: anyhow::Result<QueryOutput>,
+}
+
+/// Runs named queries on their schedules, sending each result on as it completes.
+/// Queries run concurrently, including with runs of other queries still in flight.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    hc: HoneyComb,
+    queries: Vec<ScheduledQuery>,
+}
+
+impl Scheduler {
+    pub fn new(hc: HoneyComb) -> Self {
+        Self {
+            hc,
+            queries: Vec::new(),
+        }
+    }
+
+    pub fn with_query(mut self, query: ScheduledQuery) -> Self {
+        self.queries.push(query);
+        self
+    }
+
+    /// Run until the client's cancellation token fires, failing with
+    /// [`crate::error::Cancelled`], or until the receiver is dropped or no query has a
+    /// next run, returning `Ok`. Each due query runs in its own task so a slow query
+    /// doesn't hold back the next tick; runs still in flight are aborted on return.
+    pub async fn run(self, sender: mpsc::Sender<ScheduledRun>) -> anyhow::Result<()> {
+        let hc = &self.hc;
+        let start = hc.now();
+        let mut due = self
+            .queries
+            .iter()
+            .map(|q| q.schedule.next_after(start))
+            .collect::<Vec<_>>();
+        let mut running = JoinSet::new();
+        loop {
+            while running.try_join_next().is_some() {}
+            if sender.is_closed() {
+                return Ok(());
+            }
+            let Some(next) = due.iter().flatten().min().copied() else {
+                while running.join_next().await.is_some() {}
+                return Ok(());
+            };
+            let wait = (next - hc.now()).to_std().unwrap_or_default();
+            tokio::select! {
+                result = hc.sleep(wait) => result?,
+                _ = sender.closed() => return Ok(()),
+            }
+
+            for (query, due) in self.queries.iter().zip(due.iter_mut()) {
+                if *due != Some(next) {
+                    continue;
+                }
+                *due = query.schedule.next_after(next);
+                let hc = hc.clone();
+                let query = query.clone();
+                let sender = sender.clone();
+                running.spawn(async move {
+                    let output = hc.run_query(&query.dataset, &query.spec).await;
+                    let run = ScheduledRun {
+                        name: query.name,
+                        dataset: query.dataset,
+                        due: next,
+                        output,
+                    };
+                    // A dropped receiver is noticed by the scheduling loop
+                    let _ = sender.send(run).await;
+                });
+            }
+        }
+    }
+
+    /// [`Scheduler::run`], calling `f` with each result.
+    pub async fn run_with<F>(self, mut f: F) -> anyhow::Result<()>
+    where
+        F: FnMut(ScheduledRun),
+    {
+        let (sender, mut receiver) = mpsc::channel(self.queries.len().max(1));
+        let receive = async {
+            while let Some(run) = receiver.recv().await {
+                f(run);
+            }
+        };
+        let (result, _) = tokio::join!(self.run(sender), receive);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, hour, minute, 0).unwrap()
+    }
+
+    fn field(field: &str, min: u32, max: u32) -> Vec<u32> {
+        CronField::parse(field, min, max).unwrap().0
+    }
+
+    #[test]
+    fn fields_accept_ranges_steps_and_lists() {
+        assert_eq!(field("9-12", 0, 23), vec![9, 10, 11, 12]);
+        assert_eq!(field("*/15", 0, 59), vec![0, 15, 30, 45]);
+        assert_eq!(field("5/20", 0, 59), vec![5, 25, 45]);
+        assert_eq!(field("10-20/5", 0, 59), vec![10, 15, 20]);
+        assert_eq!(field("30,0,30", 0, 59), vec![0, 30]);
+        assert_eq!(field("*", 1, 12), (1..=12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        for expression in [
+            "60 * * * *",
+            "*/0 * * * *",
+            "* * 0 * *",
+            "5-1 * * * *",
+            "* * * *",
+        ] {
+            assert!(expression.parse::<Cron>().is_err(), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn next_after_is_strictly_later() {
+        let cron: Cron = "*/15 9-17 * * *".parse().unwrap();
+        assert_eq!(cron.next_after(at(1, 9, 15)), Some(at(1, 9, 30)));
+        assert_eq!(cron.next_after(at(1, 17, 45)), Some(at(2, 9, 0)));
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either_day() {
+        // 2024-01-01 is a Monday, so the first Friday is the 5th
+        let cron: Cron = "0 0 13 * 5".parse().unwrap();
+        assert_eq!(cron.next_after(at(1, 0, 0)), Some(at(5, 0, 0)));
+        assert_eq!(cron.next_after(at(12, 0, 0)), Some(at(13, 0, 0)));
+    }
+
+    #[test]
+    fn a_day_field_starting_with_a_star_must_also_match() {
+        let cron: Cron = "0 0 */2 * 5".parse().unwrap();
+        assert_eq!(cron.next_after(at(1, 0, 0)), Some(at(5, 0, 0)));
+        let cron: Cron = "0 0 * * 5".parse().unwrap();
+        assert_eq!(cron.next_after(at(5, 0, 0)), Some(at(12, 0, 0)));
+    }
+}
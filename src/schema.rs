@@ -0,0 +1,179 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::OnceCell;
+
+use crate::{
+    error::{QueryPart, SchemaMismatch, SchemaProblem},
+    honeycomb::{Column, ColumnType, HoneyComb},
+    query::{CalcOp, FilterOp, QueryBuilder, QuerySpec},
+};
+
+/// A dataset's columns, fetched once by whichever lookup gets there first.
+type ColumnsCell = Arc<OnceCell<Arc<Vec<Column>>>>;
+
+/// Looks up dataset columns on first use and caches them, so queries can be checked
+/// against the schema before they are sent. Clones share the cache.
+///
+/// Derived columns are not returned by the Columns API, so queries referencing them
+/// are reported as using unknown columns.
+#[derive(Debug, Clone)]
+pub struct SchemaResolver {
+    hc: HoneyComb,
+    /// One cell per dataset, so a fetch holds up only lookups of the same dataset.
+    columns: Arc<Mutex<HashMap<String, ColumnsCell>>>,
+}
+
+impl SchemaResolver {
+    pub fn new(hc: HoneyComb) -> Self {
+        Self {
+            hc,
+            columns: Arc::default(),
+        }
+    }
+
+    pub async fn columns(&self, dataset_slug: &str) -> anyhow::Result<Arc<Vec<Column>>> {
+        let cell = self
+            .columns
+            .lock()
+            .unwrap()
+            .entry(dataset_slug.to_string())
+            .or_default()
+            .clone();
+        let columns = cell
+            .get_or_try_init(|| async {
+                anyhow::Ok(Arc::new(self.hc.list_all_columns(dataset_slug).await?))
+            })
+            .await?;
+        Ok(columns.clone())
+    }
+
+    /// Drop the cached columns for a dataset, e.g. after adding a column.
+    pub async fn invalidate(&self, dataset_slug: &str) {
+        self.columns.lock().unwrap().remove(dataset_slug);
+    }
+
+    /// Check every column the spec references exists, and that calculations and
+    /// ordering filters are only applied to numeric columns.
+    pub async fn validate(&self, dataset_slug: &str, spec: &QuerySpec) -> anyhow::Result<()> {
+        let columns = self.columns(dataset_slug).await?;
+        let problems = schema_problems(&columns, spec);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaMismatch {
+                dataset: dataset_slug.to_string(),
+                problems,
+            }
+            .into())
+        }
+    }
+}
+
+fn is_numeric(column: &Column) -> bool {
+    matches!(column.r#type, ColumnType::Float | ColumnType::Integer)
+}
+
+fn schema_problems(columns: &[Column], spec: &QuerySpec) -> Vec<SchemaProblem> {
+    let mut problems = Vec::new();
+    let mut check = |part: QueryPart, key_name: &str, needs_number: Option<&str>| {
+        let problem = |reason: String| SchemaProblem {
+            part,
+            column: key_name.to_string(),
+            reason,
+        };
+        match columns.iter().find(|c| c.key_name == key_name) {
+            None => problems.push(problem("no such column".to_string())),
+            Some(column) => {
+                if let Some(op) = needs_number.filter(|_| !is_numeric(column)) {
+                    problems.push(problem(format!(
+                        "{} needs a numeric column, this one is {}",
+                        op, column.r#type
+                    )));
+                }
+            }
+        }
+    };
+
+    for breakdown in &spec.breakdowns {
+        check(QueryPart::Breakdowns, &breakdown.0, None);
+    }
+    for calculation in &spec.calculations {
+        if let Some(column) = &calculation.column {
+            let numeric = !matches!(calculation.op, CalcOp::CountDistinct);
+            check(
+                QueryPart::Calculations,
+                column,
+                numeric.then_some(calculation.op.as_str()),
+            );
+        }
+    }
+    for filter in &spec.filters {
+        let numeric = matches!(
+            filter.op,
+            FilterOp::Gt | FilterOp::Ge | FilterOp::Lt | FilterOp::Le
+        );
+        check(
+            QueryPart::Filters,
+            &filter.column,
+            numeric.then_some(filter.op.as_str()),
+        );
+    }
+    for column in spec.orders.iter().filter_map(|o| o.column.as_ref()) {
+        check(QueryPart::Orders, column, None);
+    }
+    for column in spec.havings.iter().filter_map(|h| h.column.as_ref()) {
+        check(QueryPart::Havings, column, None);
+    }
+    problems
+}
+
+impl QueryBuilder {
+    /// Build the spec after checking it against the dataset's schema.
+    pub async fn build_checked(
+        self,
+        resolver: &SchemaResolver,
+        dataset_slug: &str,
+    ) -> anyhow::Result<QuerySpec> {
+        let spec = self.build();
+        resolver.validate(dataset_slug, &spec).await?;
+        Ok(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{Calculation, Filter};
+
+    fn columns() -> Vec<Column> {
+        serde_json::from_str(include_str!("../tests/fixtures/columns.json")).unwrap()
+    }
+
+    #[test]
+    fn unknown_and_non_numeric_columns_are_problems() {
+        let spec = QuerySpec::builder()
+            .calc(Calculation::new(CalcOp::P99, Some("duration_ms")))
+            .calc(Calculation::new(CalcOp::Avg, Some("http.request.method")))
+            .calc(Calculation::new(
+                CalcOp::CountDistinct,
+                Some("http.request.method"),
+            ))
+            .filter(Filter::exists("no.such.column"))
+            .build();
+        let problems = schema_problems(&columns(), &spec);
+        let columns = problems
+            .iter()
+            .map(|p| (p.part, p.column.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec![
+                (QueryPart::Calculations, "http.request.method"),
+                (QueryPart::Filters, "no.such.column")
+            ]
+        );
+    }
+}
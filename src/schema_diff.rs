@@ -0,0 +1,276 @@
+use std::fmt::{Display, Formatter};
+
+use serde::Serialize;
+
+use crate::{
+    honeycomb::{ColumnType, HoneyComb},
+    snapshot::{DatasetSnapshot, SchemaSnapshot},
+};
+
+/// A value that differs between the two sides of a diff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change<T> {
+    pub before: T,
+    pub after: T,
+}
+
+impl<T: PartialEq + Clone> Change<T> {
+    fn of(before: &T, after: &T) -> Option<Self> {
+        (before != after).then(|| Self {
+            before: before.clone(),
+            after: after.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColumnDiff {
+    pub key_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<Change<ColumnType>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<Change<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<Change<bool>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DatasetDiff {
+    pub slug: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<Change<String>>,
+    pub added_columns: Vec<String>,
+    pub removed_columns: Vec<String>,
+    pub changed_columns: Vec<ColumnDiff>,
+}
+
+/// The differences between two schemas, matching datasets by slug and columns by key
+/// name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SchemaDiff {
+    pub added_datasets: Vec<String>,
+    pub removed_datasets: Vec<String>,
+    pub changed_datasets: Vec<DatasetDiff>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_datasets.is_empty()
+            && self.removed_datasets.is_empty()
+            && self.changed_datasets.is_empty()
+    }
+}
+
+fn diff_dataset(before: &DatasetSnapshot, after: &DatasetSnapshot) -> Option<DatasetDiff> {
+    let added_columns = after
+        .columns
+        .iter()
+        .filter(|c| !before.columns.iter().any(|b| b.key_name == c.key_name))
+        .map(|c| c.key_name.clone())
+        .collect::<Vec<_>>();
+    let mut removed_columns = Vec::new();
+    let mut changed_columns = Vec::new();
+    for column in &before.columns {
+        let Some(other) = after.columns.iter().find(|c| c.key_name == column.key_name) else {
+            removed_columns.push(column.key_name.clone());
+            continue;
+        };
+        let diff = ColumnDiff {
+            key_name: column.key_name.clone(),
+            r#type: Change::of(&column.r#type, &other.r#type),
+            description: Change::of(&column.description, &other.description),
+            hidden: Change::of(&column.hidden, &other.hidden),
+        };
+        if diff.r#type.is_some() || diff.description.is_some() || diff.hidden.is_some() {
+            changed_columns.push(diff);
+        }
+    }
+    let description = Change::of(&before.dataset.description, &after.dataset.description);
+    let unchanged = description.is_none()
+        && added_columns.is_empty()
+        && removed_columns.is_empty()
+        && changed_columns.is_empty();
+    (!unchanged).then(|| DatasetDiff {
+        slug: before.dataset.slug.clone(),
+        description,
+        added_columns,
+        removed_columns,
+        changed_columns,
+    })
+}
+
+impl SchemaSnapshot {
+    /// What changed going from this snapshot to `after`.
+    pub fn diff(&self, after: &SchemaSnapshot) -> SchemaDiff {
+        let mut diff = SchemaDiff {
+            added_datasets: after
+                .datasets
+                .iter()
+                .filter(|d| self.dataset(&d.dataset.slug).is_none())
+                .map(|d| d.dataset.slug.clone())
+                .collect(),
+            ..Default::default()
+        };
+        for dataset in &self.datasets {
+            match after.dataset(&dataset.dataset.slug) {
+                None => diff.removed_datasets.push(dataset.dataset.slug.clone()),
+                Some(other) => diff.changed_datasets.extend(diff_dataset(dataset, other)),
+            }
+        }
+        diff
+    }
+}
+
+/// A plain text rendering, one line per change, e.g. for a CI log.
+impl Display for SchemaDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for slug in &self.added_datasets {
+            writeln!(f, "+ dataset {}", slug)?;
+        }
+        for slug in &self.removed_datasets {
+            writeln!(f, "- dataset {}", slug)?;
+        }
+        for dataset in &self.changed_datasets {
+            writeln!(f, "~ dataset {}", dataset.slug)?;
+            if let Some(change) = &dataset.description {
+                writeln!(
+                    f,
+                    "    description {:?} -> {:?}",
+                    change.before, change.after
+                )?;
+            }
+            for key_name in &dataset.added_columns {
+                writeln!(f, "    + {}", key_name)?;
+            }
+            for key_name in &dataset.removed_columns {
+                writeln!(f, "    - {}", key_name)?;
+            }
+            for column in &dataset.changed_columns {
+                writeln!(f, "    ~ {}", column.key_name)?;
+                if let Some(change) = &column.r#type {
+                    writeln!(f, "        type {} -> {}", change.before, change.after)?;
+                }
+                if let Some(change) = &column.description {
+                    writeln!(
+                        f,
+                        "        description {:?} -> {:?}",
+                        change.before, change.after
+                    )?;
+                }
+                if let Some(change) = &column.hidden {
+                    writeln!(f, "        hidden {} -> {}", change.before, change.after)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HoneyComb {
+    /// Compare the schema of `datasets` in this environment with another, e.g. staging
+    /// against production. Datasets missing from one side are reported as added or
+    /// removed.
+    pub async fn diff_schema_with(
+        &self,
+        other: &HoneyComb,
+        datasets: &[String],
+    ) -> anyhow::Result<SchemaDiff> {
+        let existing = |hc: &HoneyComb| {
+            let hc = hc.clone();
+            async move {
+                let slugs = hc
+                    .list_all_datasets()
+                    .await?
+                    .into_iter()
+                    .map(|d| d.slug)
+                    .filter(|slug| datasets.contains(slug))
+                    .collect::<Vec<_>>();
+                hc.take_schema_snapshot(&slugs).await
+            }
+        };
+        let (before, after) = tokio::try_join!(existing(self), existing(other))?;
+        Ok(before.diff(&after))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::honeycomb::Column;
+
+    fn snapshot() -> SchemaSnapshot {
+        let datasets: Vec<serde_json::Value> =
+            serde_json::from_str(include_str!("../tests/fixtures/datasets.json")).unwrap();
+        SchemaSnapshot {
+            taken_at: chrono::Utc::now(),
+            datasets: vec![DatasetSnapshot {
+                dataset: serde_json::from_value(datasets[0].clone()).unwrap(),
+                columns: serde_json::from_str(include_str!("../tests/fixtures/columns.json"))
+                    .unwrap(),
+                definitions: serde_json::from_str(include_str!(
+                    "../tests/fixtures/dataset_definitions.json"
+                ))
+                .unwrap(),
+            }],
+        }
+    }
+
+    fn column<'a>(snapshot: &'a mut SchemaSnapshot, key_name: &str) -> &'a mut Column {
+        snapshot.datasets[0]
+            .columns
+            .iter_mut()
+            .find(|c| c.key_name == key_name)
+            .unwrap()
+    }
+
+    #[test]
+    fn identical_snapshots_have_no_diff() {
+        assert!(snapshot().diff(&snapshot()).is_empty());
+    }
+
+    #[test]
+    fn columns_are_added_removed_and_changed() {
+        let before = snapshot();
+        let mut after = snapshot();
+        after.datasets[0]
+            .columns
+            .retain(|c| c.key_name != "app.feature_flag");
+        column(&mut after, "duration_ms").r#type = ColumnType::Integer;
+        column(&mut after, "duration_ms").hidden = true;
+        column(&mut after, "http.request.method").key_name = "http.method".to_string();
+
+        let diff = before.diff(&after);
+        let dataset = &diff.changed_datasets[0];
+        assert_eq!(dataset.added_columns, vec!["http.method"]);
+        assert_eq!(
+            dataset.removed_columns,
+            vec!["http.request.method", "app.feature_flag"]
+        );
+        assert_eq!(
+            dataset.changed_columns,
+            vec![ColumnDiff {
+                key_name: "duration_ms".to_string(),
+                r#type: Some(Change {
+                    before: ColumnType::Float,
+                    after: ColumnType::Integer,
+                }),
+                description: None,
+                hidden: Some(Change {
+                    before: false,
+                    after: true,
+                }),
+            }]
+        );
+        assert!(diff.to_string().contains("        type float -> integer\n"));
+    }
+
+    #[test]
+    fn datasets_are_matched_by_slug() {
+        let mut after = snapshot();
+        after.datasets[0].dataset.slug = "checkout-v2".to_string();
+        let diff = snapshot().diff(&after);
+        assert_eq!(diff.added_datasets, vec!["checkout-v2"]);
+        assert_eq!(diff.removed_datasets, vec!["checkout-service"]);
+        assert!(diff.changed_datasets.is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+use std::{fmt::Display, fs, path::Path};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    column::ColumnUpdate,
+    dataset::DatasetUpdate,
+    definitions::DatasetDefinitions,
+    honeycomb::{Column, Dataset, HoneyComb},
+    report::BulkReport,
+};
+
+/// A dataset's metadata, columns and definitions at a point in time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetSnapshot {
+    pub dataset: Dataset,
+    pub columns: Vec<Column>,
+    pub definitions: DatasetDefinitions,
+}
+
+/// The schema of some datasets, as kept in git or promoted between environments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaSnapshot {
+    pub taken_at: DateTime<Utc>,
+    pub datasets: Vec<DatasetSnapshot>,
+}
+
+impl SchemaSnapshot {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text =
+            fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&text).context(format!("Invalid snapshot {}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .context(format!("Failed to write {}", path.display()))
+    }
+
+    pub fn dataset(&self, dataset_slug: &str) -> Option<&DatasetSnapshot> {
+        self.datasets
+            .iter()
+            .find(|d| d.dataset.slug == dataset_slug)
+    }
+}
+
+/// One change needed to make a live dataset match a snapshot.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum SchemaChange {
+    DatasetDescription {
+        dataset: String,
+        description: String,
+    },
+    /// A column's description or hidden flag.
+    Column {
+        dataset: String,
+        key_name: String,
+        column_id: String,
+        update: ColumnUpdate,
+    },
+    /// The definitions that differ, by name.
+    Definitions {
+        dataset: String,
+        changes: Box<DatasetDefinitions>,
+    },
+}
+
+impl Display for SchemaChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DatasetDescription { dataset, .. } => write!(f, "{} description", dataset),
+            Self::Column {
+                dataset, key_name, ..
+            } => write!(f, "{}/{}", dataset, key_name),
+            Self::Definitions { dataset, .. } => write!(f, "{} definitions", dataset),
+        }
+    }
+}
+
+/// The definitions in `wanted` that map to a different column than in `live`.
+fn definition_changes(
+    wanted: &DatasetDefinitions,
+    live: &DatasetDefinitions,
+) -> anyhow::Result<Option<DatasetDefinitions>> {
+    let (Value::Object(wanted), Value::Object(live)) =
+        (serde_json::to_value(wanted)?, serde_json::to_value(live)?)
+    else {
+        return Ok(None);
+    };
+    let changes = wanted
+        .into_iter()
+        .filter_map(|(name, column)| {
+            let column_name = column.get("name")?.clone();
+            let live_name = live.get(&name).and_then(|c| c.get("name"));
+            (live_name != Some(&column_name))
+                .then(|| (name, serde_json::json!({ "name": column_name })))
+        })
+        .collect::<Map<_, _>>();
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_value(Value::Object(changes))?))
+}
+
+impl HoneyComb {
+    /// Capture the schema of `datasets`.
+    pub async fn take_schema_snapshot(
+        &self,
+        datasets: &[String],
+    ) -> anyhow::Result<SchemaSnapshot> {
+        let taken_at = self.now();
+        let datasets = stream::iter(datasets)
+            .map(|dataset_slug| async move {
+                Ok::<_, anyhow::Error>(DatasetSnapshot {
+                    dataset: self.get_dataset(dataset_slug).await?,
+                    columns: self.list_all_columns(dataset_slug).await?,
+                    definitions: self.get_dataset_definitions(dataset_slug).await?,
+                })
+            })
+            .buffered(5)
+            .try_collect()
+            .await?;
+        Ok(SchemaSnapshot { taken_at, datasets })
+    }
+
+    /// The changes that would make the live datasets match the snapshot. Datasets are
+    /// matched by slug and columns by key name, so a snapshot from another environment
+    /// can be applied. Columns missing from the live dataset are not created.
+    async fn schema_changes(
+        &self,
+        snapshot: &SchemaSnapshot,
+        report: &mut BulkReport<SchemaChange>,
+    ) -> Vec<SchemaChange> {
+        let mut changes = Vec::new();
+        for wanted in &snapshot.datasets {
+            let dataset_slug = &wanted.dataset.slug;
+            let live = async {
+                let columns = self.list_all_columns(dataset_slug).await?;
+                let dataset = self.get_dataset(dataset_slug).await?;
+                let definitions = self.get_dataset_definitions(dataset_slug).await?;
+                let definitions = definition_changes(&wanted.definitions, &definitions)?;
+                Ok::<_, anyhow::Error>((dataset, columns, definitions))
+            };
+            let (dataset, columns, definitions) = match live.await {
+                Ok(live) => live,
+                Err(e) => {
+                    report.record(dataset_slug.clone(), Err(e));
+                    continue;
+                }
+            };
+
+            if dataset.description != wanted.dataset.description {
+                changes.push(SchemaChange::DatasetDescription {
+                    dataset: dataset_slug.clone(),
+                    description: wanted.dataset.description.clone(),
+                });
+            }
+            for column in &wanted.columns {
+                let Some(live) = columns.iter().find(|c| c.key_name == column.key_name) else {
+                    report.skipped.push((
+                        format!("{}/{}", dataset_slug, column.key_name),
+                        "no such column".to_string(),
+                    ));
+                    continue;
+                };
+                let update = ColumnUpdate {
+                    description: (live.description != column.description)
+                        .then(|| column.description.clone()),
+                    hidden: (live.hidden != column.hidden).then_some(column.hidden),
+                    ..Default::default()
+                };
+                if update != ColumnUpdate::default() {
+                    changes.push(SchemaChange::Column {
+                        dataset: dataset_slug.clone(),
+                        key_name: column.key_name.clone(),
+                        column_id: live.id.clone(),
+                        update,
+                    });
+                }
+            }
+            if let Some(definitions) = definitions {
+                changes.push(SchemaChange::Definitions {
+                    dataset: dataset_slug.clone(),
+                    changes: Box::new(definitions),
+                });
+            }
+        }
+        changes
+    }
+
+    /// Apply a snapshot's dataset descriptions, column descriptions and hidden flags, and
+    /// dataset definitions to the live datasets, e.g. to promote a schema from staging to
+    /// production. Each change is reported separately; with `dry_run` nothing changes and
+    /// every change is reported as skipped, with the change as JSON.
+    pub async fn apply_schema_snapshot(
+        &self,
+        snapshot: &SchemaSnapshot,
+        dry_run: bool,
+    ) -> BulkReport<SchemaChange> {
+        let mut report = BulkReport::default();
+        let changes = self.schema_changes(snapshot, &mut report).await;
+        if dry_run {
+            report.skipped.extend(changes.into_iter().map(|change| {
+                let planned = serde_json::to_string(&change).unwrap_or_default();
+                (
+                    change.to_string(),
+                    format!("dry run, would apply {}", planned),
+                )
+            }));
+            return report;
+        }
+
+        let mut tasks = stream::iter(changes)
+            .map(|change| async move {
+                let result = match &change {
+                    SchemaChange::DatasetDescription {
+                        dataset,
+                        description,
+                    } => self
+                        .update_dataset(
+                            dataset,
+                            &DatasetUpdate {
+                                description: Some(description.clone()),
+                                ..Default::default()
+                            },
+                        )
+                        .await
+                        .map(|_| ()),
+                    SchemaChange::Column {
+                        dataset,
+                        column_id,
+                        update,
+                        ..
+                    } => self
+                        .update_column(dataset, column_id, update)
+                        .await
+                        .map(|_| ()),
+                    SchemaChange::Definitions { dataset, changes } => self
+                        .update_dataset_definitions(dataset, changes)
+                        .await
+                        .map(|_| ()),
+                };
+                (change.to_string(), result.map(|()| change))
+            })
+            .buffer_unordered(5);
+        while let Some((item, result)) = tasks.next().await {
+            report.record(item, result);
+        }
+        report
+    }
+}
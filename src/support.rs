@@ -0,0 +1,194 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use zip::write::SimpleFileOptions;
+
+use crate::{
+    error::KeyKind,
+    honeycomb::{HoneyComb, ParseMode},
+    retry::{RetryPolicy, RetrySnapshot},
+};
+
+const MAX_REQUESTS: usize = 200;
+const MAX_FAILURES: usize = 20;
+const MAX_BODY_LEN: usize = 4000;
+
+/// Metadata for one attempt at a request.
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestRecord {
+    pub at: DateTime<Utc>,
+    pub request: String,
+    pub attempt: u32,
+    pub status: Option<u16>,
+    pub elapsed: Duration,
+    pub error: Option<String>,
+}
+
+/// A request that got an error response or a response that didn't parse.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureSample {
+    pub at: DateTime<Utc>,
+    pub request: String,
+    pub status: u16,
+    pub request_body: Option<Value>,
+    pub response_body: String,
+}
+
+/// The most recent requests and failures, shared by a client and its clones.
+#[derive(Debug, Default)]
+pub(crate) struct RequestLog {
+    requests: Mutex<VecDeque<RequestRecord>>,
+    failures: Mutex<VecDeque<FailureSample>>,
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_BODY_LEN) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+impl RequestLog {
+    pub(crate) fn record(
+        &self,
+        request: String,
+        attempt: u32,
+        started: Instant,
+        status: Option<u16>,
+        error: Option<String>,
+    ) {
+        let mut requests = self.requests.lock().unwrap();
+        if requests.len() == MAX_REQUESTS {
+            requests.pop_front();
+        }
+        requests.push_back(RequestRecord {
+            at: Utc::now(),
+            request,
+            attempt,
+            status,
+            elapsed: started.elapsed(),
+            error,
+        });
+    }
+
+    pub(crate) fn sample(
+        &self,
+        request: String,
+        status: u16,
+        request_body: Option<&Value>,
+        response_body: &str,
+    ) {
+        let mut failures = self.failures.lock().unwrap();
+        if failures.len() == MAX_FAILURES {
+            failures.pop_front();
+        }
+        failures.push_back(FailureSample {
+            at: Utc::now(),
+            request,
+            status,
+            request_body: request_body.cloned(),
+            response_body: truncate(response_body),
+        });
+    }
+}
+
+/// The client configuration with the API keys reduced to a hint.
+#[derive(Debug, Serialize)]
+struct RedactedConfig {
+    crate_version: &'static str,
+    base_url: String,
+    api_key: String,
+    key_kind: KeyKind,
+    key_pool_size: Option<usize>,
+    retry_policy: RetryPolicy,
+    rate_limit_every: Option<Duration>,
+    circuit_breaker: bool,
+    parse_mode: String,
+}
+
+#[derive(Debug, Serialize)]
+struct RateLimitObservations {
+    retries: RetrySnapshot,
+    key_pool: Vec<KeyPoolObservation>,
+    open_circuits: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct KeyPoolObservation {
+    requests: u64,
+    throttled: u64,
+    resting: bool,
+}
+
+fn redact(api_key: &str) -> String {
+    let prefix = api_key.chars().take(4).collect::<String>();
+    format!("{}... ({} chars)", prefix, api_key.chars().count())
+}
+
+impl HoneyComb {
+    /// Write a zip of the redacted client configuration, recent request metadata, rate
+    /// limit observations and samples of failing payloads, to attach to bug reports.
+    pub fn generate_support_bundle(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let config = RedactedConfig {
+            crate_version: env!("CARGO_PKG_VERSION"),
+            base_url: self.base_url.clone(),
+            api_key: redact(&self.api_key),
+            key_kind: KeyKind::of(&self.api_key),
+            key_pool_size: self.key_pool.as_ref().map(|p| p.len()),
+            retry_policy: self.retry_policy.clone(),
+            rate_limit_every: self.rate_limiter.as_ref().map(|r| r.refill_every()),
+            circuit_breaker: self.circuit_breaker.is_some(),
+            parse_mode: match self.parse_mode {
+                ParseMode::Lenient => "lenient".to_string(),
+                ParseMode::Strict => "strict".to_string(),
+            },
+        };
+        let now = Instant::now();
+        let rate_limits = RateLimitObservations {
+            retries: self.retry_stats(),
+            key_pool: self
+                .key_pool
+                .iter()
+                .flat_map(|p| p.stats())
+                .map(|s| KeyPoolObservation {
+                    requests: s.requests,
+                    throttled: s.throttled,
+                    resting: s.throttled_until.is_some_and(|until| until > now),
+                })
+                .collect(),
+            open_circuits: self
+                .circuit_breaker
+                .iter()
+                .flat_map(|c| c.open_endpoints())
+                .collect(),
+        };
+        let requests = self.request_log.requests.lock().unwrap().clone();
+        let failures = self.request_log.failures.lock().unwrap().clone();
+
+        let file = File::create(path).context(format!("Failed to create {}", path.display()))?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = SimpleFileOptions::default();
+        for (name, json) in [
+            ("config.json", serde_json::to_vec_pretty(&config)?),
+            ("requests.json", serde_json::to_vec_pretty(&requests)?),
+            ("rate_limits.json", serde_json::to_vec_pretty(&rate_limits)?),
+            ("failures.json", serde_json::to_vec_pretty(&failures)?),
+        ] {
+            zip.start_file(name, options)?;
+            zip.write_all(&json)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+}
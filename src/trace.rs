@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    honeycomb::{HoneyComb, TRACE_ID},
+    query::{Filter, TimeRange},
+};
+
+/// A span row, with the standard trace columns pulled out of its fields.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Span {
+    #[serde(rename = "trace.trace_id")]
+    pub trace_id: String,
+    #[serde(rename = "trace.span_id", default)]
+    pub span_id: Option<String>,
+    /// `None` for the root span.
+    #[serde(rename = "trace.parent_id", default)]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(rename = "service.name", default)]
+    pub service_name: Option<String>,
+    #[serde(default)]
+    pub duration_ms: Option<f64>,
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Every other column of the span.
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+impl Span {
+    pub fn is_root(&self) -> bool {
+        self.parent_id.is_none()
+    }
+}
+
+impl HoneyComb {
+    /// Every span of a trace from the last 7 days, earliest first.
+    pub async fn get_trace(&self, dataset_slug: &str, trace_id: &str) -> anyhow::Result<Vec<Span>> {
+        let mut spans: Vec<Span> = self
+            .get_raw_events(
+                dataset_slug,
+                &[Filter::eq(TRACE_ID, trace_id)],
+                TimeRange::Last7Days,
+            )
+            .await?;
+        spans.sort_by_key(|s| s.timestamp);
+        Ok(spans)
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+use crate::api_enum::api_enum;
+
+api_enum! {
+    /// When a trigger notifies its recipients.
+    pub enum AlertType {
+        /// When the threshold starts or stops being exceeded.
+        OnChange => "on_change",
+        /// On every evaluation while the threshold is exceeded.
+        OnTrue => "on_true",
+    }
+}
+
+api_enum! {
+    pub enum RecipientType {
+        Email => "email",
+        Marker => "marker",
+        MsTeams => "msteams",
+        PagerDuty => "pagerduty",
+        Slack => "slack",
+        Webhook => "webhook",
+    }
+}
+
+/// Where a trigger sends its notifications.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recipient {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub recipient_type: RecipientType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
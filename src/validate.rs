@@ -0,0 +1,212 @@
+use crate::{
+    error::{InvalidQuery, QueryPart, QueryProblem},
+    query::{CalcOp, FilterOp, FilterValue, QuerySpec},
+};
+
+/// The most groups a query can return.
+pub const MAX_QUERY_LIMIT: usize = 1000;
+
+impl QuerySpec {
+    /// Check the spec for mistakes the API would reject with a 422, such as a calculation
+    /// missing its column, a filter value of the wrong shape or a having without a
+    /// matching calculation. Queries are checked before they are created.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidQuery { problems }.into())
+        }
+    }
+
+    pub fn problems(&self) -> Vec<QueryProblem> {
+        let mut problems = Vec::new();
+        let mut problem =
+            |part: QueryPart, message: String| problems.push(QueryProblem { part, message });
+
+        for calculation in &self.calculations {
+            let op = &calculation.op;
+            match (&calculation.column, op) {
+                (_, CalcOp::Other(op)) => {
+                    problem(QueryPart::Calculations, format!("unknown op {}", op))
+                }
+                (None, op) if op.needs_column() => {
+                    problem(QueryPart::Calculations, format!("{} needs a column", op))
+                }
+                (Some(column), op) if !op.needs_column() => problem(
+                    QueryPart::Calculations,
+                    format!("{} doesn't take a column, got {}", op, column),
+                ),
+                _ => {}
+            }
+        }
+
+        for filter in &self.filters {
+            let op = &filter.op;
+            let list = matches!(op, FilterOp::In | FilterOp::NotIn);
+            let message = match (op, &filter.value) {
+                (FilterOp::Other(op), _) => Some(format!("unknown op {}", op)),
+                (op, Some(_)) if !op.takes_value() => Some(format!("{} doesn't take a value", op)),
+                (op, None) if op.takes_value() => Some(format!("{} needs a value", op)),
+                (op, Some(FilterValue::List(_))) if !list => {
+                    Some(format!("{} takes a single value, not a list", op))
+                }
+                (op, Some(value)) if list && !matches!(value, FilterValue::List(_)) => {
+                    Some(format!("{} takes a list of values", op))
+                }
+                _ => None,
+            };
+            if let Some(message) = message {
+                problem(
+                    QueryPart::Filters,
+                    format!("{}: {}", filter.column, message),
+                );
+            }
+        }
+
+        if !self.havings.is_empty() && self.breakdowns.is_empty() {
+            problem(QueryPart::Havings, "havings need a breakdown".to_string());
+        }
+        for having in &self.havings {
+            if !self.calculations.contains(&having.calculation()) {
+                problem(
+                    QueryPart::Havings,
+                    format!("{} is not one of the calculations", having.calculate_op),
+                );
+            }
+            if !matches!(
+                having.op,
+                FilterOp::Eq
+                    | FilterOp::Ne
+                    | FilterOp::Gt
+                    | FilterOp::Ge
+                    | FilterOp::Lt
+                    | FilterOp::Le
+            ) {
+                problem(
+                    QueryPart::Havings,
+                    format!("{} is not a comparison", having.op),
+                );
+            }
+        }
+
+        for order in &self.orders {
+            match (&order.op, &order.column) {
+                (Some(op), column) => {
+                    let ordered = self
+                        .calculations
+                        .iter()
+                        .any(|c| c.op == *op && c.column == *column);
+                    if !ordered {
+                        problem(
+                            QueryPart::Orders,
+                            format!("{} is not one of the calculations", op),
+                        );
+                    }
+                }
+                (None, Some(column)) => {
+                    if !self.breakdowns.iter().any(|b| b.0 == *column) {
+                        problem(
+                            QueryPart::Orders,
+                            format!("{} is not one of the breakdowns", column),
+                        );
+                    }
+                }
+                (None, None) => problem(
+                    QueryPart::Orders,
+                    "an order needs an op or a column".to_string(),
+                ),
+            }
+        }
+
+        if let Some(limit) = self.limit {
+            if !(1..=MAX_QUERY_LIMIT).contains(&limit) {
+                problem(
+                    QueryPart::Limit,
+                    format!("{} is outside 1..={}", limit, MAX_QUERY_LIMIT),
+                );
+            }
+        }
+
+        if self.time_range.is_some() && self.start_time.is_some() && self.end_time.is_some() {
+            problem(
+                QueryPart::TimeRange,
+                "time_range can't be combined with both start_time and end_time".to_string(),
+            );
+        }
+        if let (Some(start), Some(end)) = (self.start_time, self.end_time) {
+            if start >= end {
+                problem(
+                    QueryPart::TimeRange,
+                    "start_time must be before end_time".to_string(),
+                );
+            }
+        }
+        if let (Some(granularity), Some(range)) = (self.granularity, self.range_seconds()) {
+            if granularity < range / 1000 || granularity > range / 10 {
+                problem(
+                    QueryPart::Granularity,
+                    format!(
+                        "{}s must be between a thousandth and a tenth of the {}s time range",
+                        granularity, range
+                    ),
+                );
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{Calculation, Filter, Having};
+
+    fn parts(spec: &QuerySpec) -> Vec<QueryPart> {
+        spec.problems().into_iter().map(|p| p.part).collect()
+    }
+
+    #[test]
+    fn limit_must_be_between_one_and_the_maximum() {
+        let spec = |limit| QuerySpec::builder().limit(limit).build();
+        assert!(spec(1).validate().is_ok());
+        assert!(spec(MAX_QUERY_LIMIT).validate().is_ok());
+        assert_eq!(parts(&spec(MAX_QUERY_LIMIT + 1)), vec![QueryPart::Limit]);
+        assert_eq!(parts(&spec(0)), vec![QueryPart::Limit]);
+    }
+
+    #[test]
+    fn calculations_need_a_column_only_when_the_op_takes_one() {
+        let spec = QuerySpec::builder()
+            .calc(Calculation::new(CalcOp::Avg, None))
+            .calc(Calculation::new(CalcOp::Count, Some("duration_ms")))
+            .calc(Calculation::new("P42", Some("duration_ms")))
+            .calc(Calculation::new(CalcOp::P99, Some("duration_ms")))
+            .build();
+        assert_eq!(parts(&spec), vec![QueryPart::Calculations; 3]);
+    }
+
+    #[test]
+    fn filter_values_must_match_the_op() {
+        let spec = QuerySpec::builder()
+            .filter(Filter::exists("error"))
+            .filter(Filter::new("error", FilterOp::Exists, Some("x".into())))
+            .filter(Filter::new("status", FilterOp::Eq, None))
+            .filter(Filter::new("status", FilterOp::In, Some("500".into())))
+            .build();
+        assert_eq!(parts(&spec), vec![QueryPart::Filters; 3]);
+    }
+
+    #[test]
+    fn havings_need_a_breakdown_and_a_matching_calculation() {
+        let having = Having::gt(Calculation::count(), 10);
+        let spec = QuerySpec::builder().having(having.clone()).build();
+        assert_eq!(parts(&spec), vec![QueryPart::Havings]);
+        let spec = QuerySpec::builder()
+            .calc(Calculation::count())
+            .breakdown("service.name")
+            .having(having)
+            .build();
+        assert!(spec.validate().is_ok());
+    }
+}
@@ -0,0 +1,208 @@
+//! Recorded API responses deserialized into the crate's types, one fixture per endpoint.
+//!
+//! To support a new endpoint record a response into `tests/fixtures/<name>.json` and
+//! add a test below that deserializes it. `every_fixture_is_exercised` fails until the
+//! new fixture is covered.
+
+use std::{fs, path::Path};
+
+use honeycomb_client::{
+    board::{Board, ChartType},
+    definitions::{DatasetDefinitions, DefinitionColumnType},
+    honeycomb::{Authorizations, Column, ColumnType, Dataset, Status},
+    query::{CalcOp, FilterOp, FilterValue, QueryResultData, QuerySpec, SavedQuery},
+    query_annotation::QueryAnnotation,
+    trace::Span,
+    trigger::{AlertType, Recipient, RecipientType},
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+const FIXTURES: &[&str] = &[
+    "auth",
+    "batch",
+    "boards",
+    "columns",
+    "dataset_definitions",
+    "datasets",
+    "query",
+    "query_annotations",
+    "query_results",
+    "trace_results",
+    "triggers",
+];
+
+fn fixture<T: DeserializeOwned>(name: &str) -> T {
+    assert!(
+        FIXTURES.contains(&name),
+        "fixture {} is not registered",
+        name
+    );
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(format!("{}.json", name));
+    let text = fs::read_to_string(&path).unwrap();
+    serde_json::from_str(&text)
+        .unwrap_or_else(|e| panic!("{} does not deserialize: {}", path.display(), e))
+}
+
+#[test]
+fn every_fixture_is_exercised() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        assert!(FIXTURES.contains(&name), "fixture {} has no test", name);
+    }
+}
+
+#[test]
+fn auth() {
+    let auth: Authorizations = fixture("auth");
+    assert_eq!(auth.environment.slug, "production");
+    assert!(auth.has_required_access(&["queries", "events"]));
+    assert_eq!(
+        auth.missing_access(&["queries", "columns"]),
+        vec!["columns"]
+    );
+}
+
+#[test]
+fn batch() {
+    let statuses: Vec<Status> = fixture("batch");
+    assert_eq!(statuses[0].status, 202);
+    assert!(statuses[1].error.is_some());
+}
+
+#[test]
+fn columns() {
+    let columns: Vec<Column> = fixture("columns");
+    assert_eq!(columns.len(), 4);
+    assert_eq!(columns[0].r#type, ColumnType::Float);
+    assert_eq!(columns[1].r#type, ColumnType::String);
+    assert!(columns[2].hidden);
+    assert!(columns[3].last_written.is_none());
+    assert!(columns[0].created_at.is_some());
+}
+
+#[test]
+fn dataset_definitions() {
+    let definitions: DatasetDefinitions = fixture("dataset_definitions");
+    assert_eq!(definitions.trace_id.unwrap().name, "trace.trace_id");
+    let status = definitions.status.unwrap();
+    assert_eq!(
+        status.column_type,
+        Some(DefinitionColumnType::DerivedColumn)
+    );
+    assert!(definitions.user.is_none());
+    assert!(definitions.extra.contains_key("log_severity"));
+}
+
+#[test]
+fn datasets() {
+    let datasets: Vec<Dataset> = fixture("datasets");
+    assert_eq!(datasets[0].slug, "checkout-service");
+    assert_eq!(datasets[0].regular_columns_count, 112);
+    assert!(datasets[0].settings.delete_protected);
+    assert!(datasets[0].created_at.is_some());
+    assert!(datasets[1].last_written_at.is_none());
+}
+
+#[test]
+fn query() {
+    let value: Value = fixture("query");
+    let query: SavedQuery = fixture("query");
+    assert_eq!(query.id, "abc123");
+    assert_eq!(query.spec.calculations[0].op, CalcOp::P99);
+    assert_eq!(query.spec.filters[0].value, Some(FilterValue::Integer(500)));
+    assert_eq!(query.spec.granularity, Some(60));
+    assert_eq!(serde_json::to_value(&query).unwrap(), value);
+}
+
+#[test]
+fn query_annotations() {
+    let annotations: Vec<QueryAnnotation> = fixture("query_annotations");
+    assert_eq!(annotations[0].name, "Checkout latency");
+    assert_eq!(annotations[0].query_id, "abc123");
+}
+
+#[test]
+fn query_results() {
+    let value: Value = fixture("query_results");
+    assert_eq!(value["complete"], true);
+    assert_eq!(value["data"]["results"].as_array().unwrap().len(), 2);
+    let spec: QuerySpec = serde_json::from_value(value["query"].clone()).unwrap();
+    assert_eq!(spec.breakdowns[0].0, "http.request.method");
+    assert_eq!(spec.to_value(), value["query"]);
+
+    #[derive(Deserialize)]
+    struct MethodCount {
+        #[serde(rename = "http.request.method")]
+        method: String,
+        #[serde(rename = "COUNT")]
+        count: u64,
+    }
+    let data: QueryResultData<MethodCount> = fixture("query_results");
+    assert!(data.complete);
+    assert_eq!(data.results[1].method, "POST");
+    assert_eq!(data.results[1].count, 210);
+    assert!(data.query_url.unwrap().ends_with("sGUnkBHgRFN"));
+    // Results serialize back to the API's shape
+    let data: QueryResultData = fixture("query_results");
+    assert_eq!(serde_json::to_value(data).unwrap(), value);
+}
+
+#[test]
+fn trace_results() {
+    let data: QueryResultData<Span> = fixture("trace_results");
+    let spans = &data.results;
+    assert!(!spans[0].is_root() && spans[1].is_root());
+    assert_eq!(spans[0].service_name.as_deref(), Some("checkout-service"));
+    assert_eq!(spans[0].fields["db.system"], "postgresql");
+    assert!(spans[1].timestamp < spans[0].timestamp);
+}
+
+#[test]
+fn triggers() {
+    let triggers: Vec<Value> = fixture("triggers");
+    assert_eq!(triggers[0]["name"], "High error rate");
+    let spec: QuerySpec = serde_json::from_value(triggers[0]["query"].clone()).unwrap();
+    assert_eq!(spec.filters[0].op, FilterOp::Exists);
+    let alert_type: AlertType = serde_json::from_value(triggers[0]["alert_type"].clone()).unwrap();
+    assert_eq!(alert_type, AlertType::OnChange);
+    let recipients: Vec<Recipient> =
+        serde_json::from_value(triggers[0]["recipients"].clone()).unwrap();
+    assert_eq!(recipients[0].recipient_type, RecipientType::Email);
+}
+
+#[test]
+fn unknown_trigger_values_round_trip() {
+    let recipient = json!({"id": "abc123", "type": "opsgenie", "target": "checkout"});
+    let parsed: Recipient = serde_json::from_value(recipient.clone()).unwrap();
+    assert_eq!(
+        parsed.recipient_type,
+        RecipientType::Other("opsgenie".to_string())
+    );
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), recipient);
+    let alert_type: AlertType = serde_json::from_value(json!("on_resolve")).unwrap();
+    assert_eq!(alert_type, AlertType::Other("on_resolve".to_string()));
+    assert_eq!(serde_json::to_value(&alert_type).unwrap(), "on_resolve");
+}
+
+#[test]
+fn boards() {
+    let boards: Vec<Board> = fixture("boards");
+    assert_eq!(boards[0].name, "Checkout overview");
+    let query = &boards[0].queries[0];
+    assert_eq!(query.chart_type, Some(ChartType::Line));
+    let settings = query.graph_settings.as_ref().unwrap();
+    assert!(settings.log_scale && settings.omit_missing_values);
+    assert!(!settings.overlaid_charts);
+    // Unmodelled fields survive a round trip
+    let value = serde_json::to_value(&boards[0]).unwrap();
+    assert!(value["links"]["board_url"].is_string());
+    assert_eq!(
+        value["queries"][0]["graph_settings"]["prefer_overlaid_charts"],
+        true
+    );
+}
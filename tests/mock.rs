@@ -0,0 +1,329 @@
+//! Requests against the `test-util` mock server, for behaviour that depends on response
+//! status rather than body shape.
+
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use honeycomb_client::{
+    cache::QueryCache,
+    circuit_breaker::CircuitBreaker,
+    column::{key_names, ColumnAction},
+    error::{DeleteProtected, KeyKind, StatusError},
+    key_pool::KeyPool,
+    mock::{MockServer, RateLimit},
+    query::{Calculation, QueryOutput, QuerySpec, TimeRange},
+    report::ReportSection,
+    retry::RetryPolicy,
+};
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn delete_not_found_is_an_error() {
+    let server = MockServer::start().await.unwrap();
+    server.mock(
+        "DELETE",
+        "query_annotations/checkout/abc123",
+        404,
+        json!({"error": "not found"}),
+    );
+    let e = server
+        .client()
+        .delete_query_annotation("checkout", "abc123")
+        .await
+        .unwrap_err();
+    let e = e.downcast_ref::<StatusError>().unwrap();
+    assert!(e.is_not_found());
+    assert!(e.body.contains("not found"));
+}
+
+#[tokio::test]
+async fn delete_unknown_column_is_not_found() {
+    let server = MockServer::start().await.unwrap();
+    let e = server
+        .client()
+        .delete_column("checkout", "no-such-column")
+        .await
+        .unwrap_err();
+    assert!(e.downcast_ref::<StatusError>().unwrap().is_not_found());
+}
+
+#[tokio::test]
+async fn delete_dataset_errors() {
+    let server = MockServer::start().await.unwrap();
+    server.mock(
+        "DELETE",
+        "datasets/checkout",
+        409,
+        json!({"error": "dataset is delete protected"}),
+    );
+    let hc = server.client();
+    let e = hc.delete_dataset("checkout").await.unwrap_err();
+    assert!(e.is::<DeleteProtected>());
+    let e = hc.delete_dataset("checkuot").await.unwrap_err();
+    assert!(e.downcast_ref::<StatusError>().unwrap().is_not_found());
+}
+
+#[tokio::test]
+async fn bulk_delete_reports_missing_columns_as_failed() {
+    let server = MockServer::start().await.unwrap();
+    let columns: Value = serde_json::from_str(include_str!("fixtures/columns.json")).unwrap();
+    server.mock("GET", "columns/checkout", 200, columns);
+    server.mock("DELETE", "columns/checkout/aBcD123", 200, json!({}));
+    let report = server
+        .client()
+        .bulk_column_action(
+            &["checkout".to_string()],
+            key_names(&["duration_ms", "http.request.method"]),
+            ColumnAction::Delete,
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(report.succeeded.len(), 1);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, "checkout/http.request.method");
+}
+
+#[tokio::test]
+async fn client_errors_do_not_trip_the_circuit_breaker() {
+    let server = MockServer::start().await.unwrap();
+    let hc = server
+        .client()
+        .with_circuit_breaker(CircuitBreaker::new(1, Duration::from_secs(60)));
+    for _ in 0..2 {
+        let e = hc
+            .delete_column("checkout", "no-such-column")
+            .await
+            .unwrap_err();
+        assert!(e.is::<StatusError>());
+    }
+    assert!(hc.circuit_breaker.unwrap().open_endpoints().is_empty());
+}
+
+/// A server whose key can't run queries, with the columns fixture for `checkout`.
+async fn without_query_access() -> MockServer {
+    let server = MockServer::start().await.unwrap();
+    let mut auth: Value = serde_json::from_str(include_str!("fixtures/auth.json")).unwrap();
+    auth["api_key_access"]["queries"] = false.into();
+    server.mock("GET", "auth", 200, auth);
+    let columns: Value = serde_json::from_str(include_str!("fixtures/columns.json")).unwrap();
+    server.mock("GET", "columns/checkout", 200, columns);
+    server
+}
+
+#[tokio::test]
+async fn cardinality_report_is_skipped_without_query_access() {
+    let server = without_query_access().await;
+    let report = server
+        .client()
+        .cardinality_report(&["checkout".to_string()], TimeRange::LastHour, 1000)
+        .await
+        .unwrap();
+    assert!(
+        matches!(report, ReportSection::Skipped(reason) if reason == "missing access: queries")
+    );
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn volume_estimate_is_skipped_without_query_access() {
+    let server = without_query_access().await;
+    let report = server
+        .client()
+        .estimate_event_volume(&["checkout".to_string()], TimeRange::Last7Days)
+        .await
+        .unwrap();
+    assert!(report.complete().is_none());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn type_mismatches_are_skipped_without_query_access() {
+    let server = without_query_access().await;
+    let report = server
+        .client()
+        .detect_type_mismatches(&["checkout".to_string()])
+        .await
+        .unwrap();
+    assert!(report.complete().is_none());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn unused_column_audit_is_skipped_without_query_access() {
+    let server = without_query_access().await;
+    let report = server
+        .client()
+        .audit_unused_columns(&["checkout".to_string()], TimeRange::Last7Days)
+        .await
+        .unwrap();
+    assert!(report.complete().is_none());
+    assert_eq!(server.requests().len(), 1);
+}
+
+/// Answer a query on `checkout` with `results` as the result rows.
+fn mock_query(server: &MockServer, results: Value) {
+    let query: Value = serde_json::from_str(include_str!("fixtures/query.json")).unwrap();
+    server.mock("POST", "queries/checkout", 200, query);
+    server.mock(
+        "POST",
+        "query_results/checkout",
+        200,
+        json!({"id": "r1", "links": {"query_url": "https://ui.honeycomb.io/r1"}}),
+    );
+    server.mock(
+        "GET",
+        "query_results/checkout/r1",
+        200,
+        json!({"id": "r1", "complete": true, "data": {"series": [], "results": results}}),
+    );
+}
+
+#[tokio::test]
+async fn cardinality_without_a_count_is_an_error() {
+    let server = MockServer::start().await.unwrap();
+    let hc = server.client();
+    mock_query(&server, json!([]));
+    let distinct = hc
+        .get_cardinality("checkout", "user.id", TimeRange::LastHour)
+        .await
+        .unwrap();
+    assert_eq!(distinct, 0);
+    mock_query(
+        &server,
+        json!([{"data": {"COUNT_DISTINCT(user.id)": 12.5}}]),
+    );
+    assert!(hc
+        .get_cardinality("checkout", "user.id", TimeRange::LastHour)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn a_rate_limit_of_zero_throttles_every_request() {
+    let server = MockServer::start().await.unwrap();
+    server.set_rate_limit(Some(RateLimit {
+        requests: 0,
+        per: Duration::from_secs(1),
+    }));
+    let hc = server.client().with_retry_policy(RetryPolicy {
+        max_attempts: 1,
+        ..Default::default()
+    });
+    assert!(hc.list_all_datasets().await.is_err());
+    assert_eq!(server.throttled_count(), 1);
+}
+
+#[tokio::test]
+async fn zero_max_attempts_sends_the_request_once() {
+    let server = MockServer::start().await.unwrap();
+    server.mock("GET", "datasets", 200, json!([]));
+    let hc = server.client().with_retry_policy(RetryPolicy {
+        max_attempts: 0,
+        ..Default::default()
+    });
+    hc.list_all_datasets().await.unwrap();
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn retry_after_is_capped_at_max_delay() {
+    let server = MockServer::start().await.unwrap();
+    server.set_rate_limit(Some(RateLimit {
+        requests: 0,
+        per: Duration::from_secs(3600),
+    }));
+    let hc = server.client().with_retry_policy(RetryPolicy {
+        max_attempts: 2,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(10),
+        jitter: false,
+    });
+    let result = tokio::time::timeout(Duration::from_secs(5), hc.list_all_datasets()).await;
+    assert!(result.unwrap().is_err());
+    assert_eq!(server.throttled_count(), 2);
+}
+
+#[tokio::test]
+async fn capabilities_describe_the_key_pool() {
+    let server = MockServer::start().await.unwrap();
+    let key_pool = KeyPool::new(vec!["hcamk_one".to_string(), "hcamk_two".to_string()]).unwrap();
+    let capabilities = server
+        .client()
+        .with_key_pool(key_pool)
+        .capabilities()
+        .await
+        .unwrap();
+    assert_eq!(capabilities.key_kind, KeyKind::Management);
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn clients_on_different_hosts_do_not_share_cached_results() {
+    let cache = QueryCache::memory(Duration::from_secs(60));
+    let auth: Value = serde_json::from_str(include_str!("fixtures/auth.json")).unwrap();
+    let spec = QuerySpec::builder()
+        .calc(Calculation::count())
+        .range(TimeRange::LastHour)
+        .build();
+    let mut servers = Vec::new();
+    for count in [1, 2] {
+        let server = MockServer::start().await.unwrap();
+        server.mock("GET", "auth", 200, auth.clone());
+        mock_query(&server, json!([{"data": {"COUNT": count}}]));
+        servers.push(server);
+    }
+    for (server, count) in servers.iter().zip([1, 2]) {
+        let hc = server.client().with_query_cache(cache.clone());
+        for _ in 0..2 {
+            let output: QueryOutput = hc.run_query("checkout", &spec).await.unwrap();
+            assert_eq!(output.results[0]["COUNT"], count);
+        }
+        // Only the first run reaches the server
+        let queries = server
+            .requests()
+            .iter()
+            .filter(|r| r.path == "queries/checkout")
+            .count();
+        assert_eq!(queries, 1);
+    }
+}
+
+/// A server with the fixtures for the `checkout-service` dataset, its columns and its
+/// definitions.
+fn with_checkout_schema(server: &MockServer) {
+    let datasets: Vec<Value> =
+        serde_json::from_str(include_str!("fixtures/datasets.json")).unwrap();
+    server.mock("GET", "datasets/checkout-service", 200, datasets[0].clone());
+    let columns: Value = serde_json::from_str(include_str!("fixtures/columns.json")).unwrap();
+    server.mock("GET", "columns/checkout-service", 200, columns);
+    let definitions: Value =
+        serde_json::from_str(include_str!("fixtures/dataset_definitions.json")).unwrap();
+    server.mock(
+        "GET",
+        "dataset_definitions/checkout-service",
+        200,
+        definitions,
+    );
+}
+
+#[tokio::test]
+async fn applying_a_snapshot_of_the_live_schema_changes_nothing() {
+    let server = MockServer::start().await.unwrap();
+    with_checkout_schema(&server);
+    let hc = server.client();
+    let mut snapshot = hc
+        .take_schema_snapshot(&["checkout-service".to_string()])
+        .await
+        .unwrap();
+    let report = hc.apply_schema_snapshot(&snapshot, false).await;
+    assert!(report.succeeded.is_empty());
+    assert!(report.failed.is_empty() && report.skipped.is_empty());
+    assert!(server.requests().iter().all(|r| r.method == "GET"));
+
+    snapshot.datasets[0].columns[0].description = "Request latency".to_string();
+    let report = hc.apply_schema_snapshot(&snapshot, true).await;
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].0, "checkout-service/duration_ms");
+}